@@ -0,0 +1,117 @@
+//! Benchmarks for `load_obj`/`load_mtl` over synthetic content.
+//!
+//! The obj inputs are square grids of quads of increasing size, and single
+//! faces with an increasing number of elements on one line. Parsing should
+//! scale linearly with the number of faces and elements, so the reported
+//! throughput (elements per second) should stay roughly constant across the
+//! sizes in each group.
+
+use std::fmt::Write;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Builds an obj file containing a `size` x `size` grid of quads, with
+/// texture coordinates and normals for every vertex.
+fn grid_obj(size: usize) -> String {
+    let verts = size + 1;
+    let mut out = String::new();
+    out.push_str("mtllib grid.mtl\no grid\ng grid\nusemtl default\ns 1\n");
+    for y in 0..verts {
+        for x in 0..verts {
+            let _ = writeln!(out, "v {}.0 {}.0 0.5", x, y);
+            let _ = writeln!(
+                out,
+                "vt {:.4} {:.4}",
+                x as f32 / size as f32,
+                y as f32 / size as f32
+            );
+            out.push_str("vn 0.0 0.0 1.0\n");
+        }
+    }
+    for y in 0..size {
+        for x in 0..size {
+            let a = y * verts + x + 1;
+            let b = a + 1;
+            let c = a + verts + 1;
+            let d = a + verts;
+            let _ = writeln!(
+                out,
+                "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c} {d}/{d}/{d}",
+                a = a,
+                b = b,
+                c = c,
+                d = d
+            );
+        }
+    }
+    out
+}
+
+/// Builds an obj file containing a single face with `count` elements, all on
+/// one line.
+fn long_face_obj(count: usize) -> String {
+    let mut out = String::new();
+    for i in 0..count {
+        let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+        let _ = writeln!(out, "v {} {} 0.0", angle.cos(), angle.sin());
+    }
+    out.push('f');
+    for i in 1..=count {
+        let _ = write!(out, " {}", i);
+    }
+    out.push('\n');
+    out
+}
+
+/// Builds an mtl file with `count` materials.
+fn materials(count: usize) -> String {
+    let mut out = String::new();
+    for i in 0..count {
+        let _ = writeln!(out, "newmtl material_{}", i);
+        out.push_str("Ka 0.2 0.2 0.2\nKd 0.6 0.6 0.6\nKs 0.1 0.1 0.1\nd 1\nNs 200\nillum 2\n");
+        let _ = writeln!(out, "map_Kd -s 1 1 1 -o 0 0 0 -mm 0 1 texture_{}.png", i);
+    }
+    out
+}
+
+fn load_obj(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_obj");
+    group.sample_size(10);
+    for size in [32, 64, 128, 256] {
+        let input = grid_obj(size);
+        group.throughput(Throughput::Elements((size * size) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size * size), &input, |b, i| {
+            b.iter(|| nobject_rs::load_obj(i).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn load_obj_long_line(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_obj_long_line");
+    group.sample_size(10);
+    for count in [10_000, 20_000, 40_000, 80_000] {
+        let input = long_face_obj(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &input, |b, i| {
+            b.iter(|| nobject_rs::load_obj(i).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn load_mtl(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_mtl");
+    group.sample_size(10);
+    for count in [100, 1_000, 10_000] {
+        let input = materials(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &input, |b, i| {
+            b.iter(|| nobject_rs::load_mtl(i).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, load_obj, load_obj_long_line, load_mtl);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+use std::{fmt, ops::Deref};
+
+use derive_more::{Constructor, Into};
+
+use crate::{ParseMode, ParseOptions, Span};
+
+/// How serious a `Diagnostic` is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The statement was kept, but part of it had to be interpreted
+    /// or replaced.
+    Warning,
+    /// The statement couldn't be parsed and was skipped.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem in the input which parsing recovered from.
+#[derive(Clone, Constructor, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Description of the problem.
+    pub message: String,
+    /// Where in the input the problem was found.
+    pub span: Span,
+    /// The keyword of the statement the problem was found in, such as `f`
+    /// or `Kd`. This is `None` when the statement wasn't recognized.
+    pub statement: Option<&'static str>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}: {}", self.severity, self.span, self.message)
+    }
+}
+
+/// The collection of problems recovered from while parsing,
+/// in the order they were found.
+#[derive(Clone, Debug, Default, Into, PartialEq, Eq)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    /// Iterates over the diagnostics with `Severity::Warning`.
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(|d| d.severity == Severity::Warning)
+    }
+
+    /// Iterates over the diagnostics with `Severity::Error`.
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(|d| d.severity == Severity::Error)
+    }
+
+    /// Records `diagnostic`, or hands it back if the `options` say it has to
+    /// stop parsing instead.
+    pub(crate) fn record(
+        &mut self,
+        diagnostic: Diagnostic,
+        options: &ParseOptions,
+    ) -> Result<(), Diagnostic> {
+        let fatal = options.diagnostics_as_errors
+            || (diagnostic.severity == Severity::Error && options.mode == ParseMode::Strict);
+        if fatal {
+            Err(diagnostic)
+        } else {
+            self.0.push(diagnostic);
+            Ok(())
+        }
+    }
+}
+
+impl Deref for Diagnostics {
+    type Target = [Diagnostic];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
@@ -0,0 +1,634 @@
+use std::result::Result;
+
+use derive_more::{Constructor, From, Into};
+use nom::{
+    branch::alt,
+    combinator::{map, map_res, opt},
+    error,
+    multi::many1,
+    sequence::preceded,
+    IResult, Parser,
+};
+
+use crate::{
+    float, get_token_string, int,
+    model::{parse_face_element, FaceElement},
+    tokenizer::{Token, TokenSet},
+};
+
+/// The kinds of basis free-form curves and surfaces can use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Basis {
+    /// Basis matrix, given with `bmat` statements.
+    BasisMatrix,
+    /// Bezier
+    Bezier,
+    /// B-spline
+    BSpline,
+    /// Cardinal
+    Cardinal,
+    /// Taylor
+    Taylor,
+}
+
+/// The type of free-form geometry, as set by the `cstype` statement.
+#[derive(Clone, Copy, Constructor, Debug, From, Into, PartialEq, Eq)]
+pub struct CurveSurfaceType {
+    /// Whether the curve or surface is rational, which means the
+    /// control points have a weight.
+    pub rational: bool,
+    /// The basis used by the curve or surface.
+    pub basis: Basis,
+}
+
+/// A parameter direction of a curve or surface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The u direction, which is the only direction for curves.
+    U,
+    /// The v direction, which is only used by surfaces.
+    V,
+}
+
+/// The state set by the free-form attribute statements, which applies to
+/// every curve and surface following them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FreeFormAttributes {
+    /// The type of curve or surface.
+    /// Corresponds to `cstype` in the specification.
+    pub curve_type: Option<CurveSurfaceType>,
+    /// The degree in the u direction.
+    /// Corresponds to `deg` in the specification.
+    pub degree_u: Option<u32>,
+    /// The degree in the v direction, for surfaces.
+    /// Corresponds to `deg` in the specification.
+    pub degree_v: Option<u32>,
+    /// The basis matrix in the u direction, in row major order.
+    /// Corresponds to `bmat u` in the specification.
+    pub basis_matrix_u: Option<Vec<f32>>,
+    /// The basis matrix in the v direction, in row major order.
+    /// Corresponds to `bmat v` in the specification.
+    pub basis_matrix_v: Option<Vec<f32>>,
+    /// The step size in the u direction.
+    /// Corresponds to `step` in the specification.
+    pub step_u: Option<f32>,
+    /// The step size in the v direction.
+    /// Corresponds to `step` in the specification.
+    pub step_v: Option<f32>,
+}
+
+/// How a curve is subdivided into line segments when it's tessellated.
+/// Corresponds to `ctech` in the specification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveApproximation {
+    /// Every polynomial segment of the curve is subdivided `resolution`
+    /// times in parameter space.
+    Parametric {
+        /// Number of subdivisions per polynomial segment.
+        resolution: f32,
+    },
+    /// The curve is subdivided until no line segment is longer than
+    /// `max_length`.
+    Spatial {
+        /// Maximum length of a line segment.
+        max_length: f32,
+    },
+    /// The curve is subdivided until every line segment is within
+    /// `max_distance` of the curve, and turns by at most `max_angle`.
+    Curvature {
+        /// Maximum distance between a line segment and the curve.
+        max_distance: f32,
+        /// Maximum angle, in degrees, between the tangents at the ends
+        /// of a line segment.
+        max_angle: f32,
+    },
+}
+
+/// How a surface is subdivided into polygons when it's tessellated.
+/// Corresponds to `stech` in the specification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurfaceApproximation {
+    /// Every polynomial segment of the surface is subdivided `u_resolution`
+    /// times in the u direction and `v_resolution` times in the v direction.
+    ParametricA {
+        /// Number of subdivisions per polynomial segment in the u direction.
+        u_resolution: f32,
+        /// Number of subdivisions per polynomial segment in the v direction.
+        v_resolution: f32,
+    },
+    /// Every polynomial segment of the surface is subdivided `resolution`
+    /// times in both directions.
+    ParametricB {
+        /// Number of subdivisions per polynomial segment.
+        resolution: f32,
+    },
+    /// The surface is subdivided until no polygon edge is longer than
+    /// `max_length`.
+    Spatial {
+        /// Maximum length of a polygon edge.
+        max_length: f32,
+    },
+    /// The surface is subdivided until every polygon is within
+    /// `max_distance` of the surface, and adjacent edges turn by at most
+    /// `max_angle`.
+    Curvature {
+        /// Maximum distance between a polygon and the surface.
+        max_distance: f32,
+        /// Maximum angle, in degrees, between the tangents at the ends
+        /// of a polygon edge.
+        max_angle: f32,
+    },
+}
+
+/// A segment of a 2D curve used by trimming loops, holes and special curves.
+#[derive(Copy, Clone, Constructor, Debug, Default, From, Into, PartialEq)]
+pub struct CurveReference {
+    /// The starting parameter value on the curve.
+    pub start: f32,
+    /// The ending parameter value on the curve.
+    pub end: f32,
+    /// Index of the `curv2` curve. Note that these START at 1, NOT 0.
+    /// Relative indices are resolved when the statement is parsed.
+    pub curve_index: i32,
+}
+
+/// One side of a connection: a curve on the edge of a surface.
+#[derive(Copy, Clone, Constructor, Debug, Default, From, Into, PartialEq)]
+pub struct SurfaceEdge {
+    /// Index of the surface. Note that these START at 1, NOT 0.
+    /// Relative indices are resolved when the statement is parsed.
+    pub surface_index: i32,
+    /// The starting parameter value on the curve.
+    pub start: f32,
+    /// The ending parameter value on the curve.
+    pub end: f32,
+    /// Index of the `curv2` curve. Note that these START at 1, NOT 0.
+    /// Relative indices are resolved when the statement is parsed.
+    pub curve_index: i32,
+}
+
+/// Connectivity between two surfaces, which share the curves along their
+/// edges. Corresponds to `con` in the specification.
+#[derive(Copy, Clone, Constructor, Debug, Default, From, Into, PartialEq)]
+pub struct Connection {
+    /// The edge of the first surface.
+    pub first: SurfaceEdge,
+    /// The edge of the second surface.
+    pub second: SurfaceEdge,
+}
+
+/// The statements found between a curve or surface and its `end` statement.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FreeFormBody {
+    /// Global parameter values in the u direction, such as the knot vector
+    /// of a B-spline. Corresponds to `parm u` in the specification.
+    pub parameters_u: Vec<f32>,
+    /// Global parameter values in the v direction.
+    /// Corresponds to `parm v` in the specification.
+    pub parameters_v: Vec<f32>,
+    /// Outer trimming loops, each built from one or more curve segments.
+    /// Corresponds to `trim` in the specification.
+    pub trims: Vec<Vec<CurveReference>>,
+    /// Inner trimming loops, each built from one or more curve segments.
+    /// Corresponds to `hole` in the specification.
+    pub holes: Vec<Vec<CurveReference>>,
+    /// Special curves which have to be part of any tessellation.
+    /// Corresponds to `scrv` in the specification.
+    pub special_curves: Vec<Vec<CurveReference>>,
+    /// Parameter space vertex indices of special points which have to be
+    /// part of any tessellation. Note that these START at 1, NOT 0.
+    /// Relative indices are resolved when the statement is parsed.
+    /// Corresponds to `sp` in the specification.
+    pub special_points: Vec<i32>,
+}
+
+/// A free-form curve in 3D space.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Curve {
+    /// The attributes in effect when the curve was defined.
+    pub attributes: FreeFormAttributes,
+    /// The starting parameter value of the curve.
+    pub start: f32,
+    /// The ending parameter value of the curve.
+    pub end: f32,
+    /// Control point vertex indices. Note that these START at 1, NOT 0.
+    /// Relative indices are resolved when the statement is parsed.
+    pub vertices: Vec<i32>,
+    /// The body statements of the curve.
+    pub body: FreeFormBody,
+    /// The groups which were current when the curve was defined.
+    pub groups: Vec<String>,
+    /// The position in `Model::objects` of the object which was current
+    /// when the curve was defined, if any.
+    pub object: Option<usize>,
+    /// The material which was current when the curve was defined, if any.
+    pub material_name: Option<String>,
+    /// The approximation technique which was current when the curve was
+    /// defined, if any.
+    pub technique: Option<CurveApproximation>,
+}
+
+/// A free-form curve in the parameter space of a surface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Curve2D {
+    /// The attributes in effect when the curve was defined.
+    pub attributes: FreeFormAttributes,
+    /// Control point parameter space vertex indices.
+    /// Note that these START at 1, NOT 0.
+    /// Relative indices are resolved when the statement is parsed.
+    pub param_vertices: Vec<i32>,
+    /// The body statements of the curve.
+    pub body: FreeFormBody,
+    /// The approximation technique which was current when the curve was
+    /// defined, if any.
+    pub technique: Option<CurveApproximation>,
+}
+
+/// A free-form surface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Surface {
+    /// The attributes in effect when the surface was defined.
+    pub attributes: FreeFormAttributes,
+    /// The starting parameter value in the u direction.
+    pub start_u: f32,
+    /// The ending parameter value in the u direction.
+    pub end_u: f32,
+    /// The starting parameter value in the v direction.
+    pub start_v: f32,
+    /// The ending parameter value in the v direction.
+    pub end_v: f32,
+    /// Control points, with optional texture and normal indices.
+    /// Relative indices are resolved when the statement is parsed.
+    pub vertices: Vec<FaceElement>,
+    /// The body statements of the surface.
+    pub body: FreeFormBody,
+    /// The groups which were current when the surface was defined.
+    pub groups: Vec<String>,
+    /// The smoothing group which was current when the surface was defined.
+    pub smoothing_group: i32,
+    /// The merging group which was current when the surface was defined.
+    pub merging_group: i32,
+    /// The position in `Model::objects` of the object which was current
+    /// when the surface was defined, if any.
+    pub object: Option<usize>,
+    /// The material which was current when the surface was defined, if any.
+    pub material_name: Option<String>,
+    /// The approximation technique which was current when the surface was
+    /// defined, if any.
+    pub technique: Option<SurfaceApproximation>,
+}
+
+/// The curve or surface whose body is currently open.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum OpenBody {
+    Curve,
+    Curve2D,
+    Surface,
+}
+
+/// Holds the free-form geometry of a model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FreeForm {
+    /// Collection of curves in 3D space.
+    pub curves: Vec<Curve>,
+    /// Collection of curves in parameter space.
+    pub curves_2d: Vec<Curve2D>,
+    /// Collection of surfaces.
+    pub surfaces: Vec<Surface>,
+    /// Collection of connections between surfaces.
+    pub connections: Vec<Connection>,
+
+    attributes: FreeFormAttributes,
+    open_body: Option<OpenBody>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum FreeFormElement {
+    CurveSurfaceType(CurveSurfaceType),
+    Degree(u32, Option<u32>),
+    BasisMatrix(Direction, Vec<f32>),
+    Step(f32, Option<f32>),
+    Curve(Curve),
+    Curve2D(Curve2D),
+    Surface(Surface),
+    Parameter(Direction, Vec<f32>),
+    Trim(Vec<CurveReference>),
+    Hole(Vec<CurveReference>),
+    SpecialCurve(Vec<CurveReference>),
+    SpecialPoint(Vec<i32>),
+    End,
+    Connection(Connection),
+}
+
+impl FreeForm {
+    pub(crate) fn apply(&mut self, element: FreeFormElement) -> Result<(), String> {
+        match element {
+            FreeFormElement::CurveSurfaceType(t) => self.attributes.curve_type = Some(t),
+            FreeFormElement::Degree(u, v) => {
+                self.attributes.degree_u = Some(u);
+                self.attributes.degree_v = v;
+            },
+            FreeFormElement::BasisMatrix(Direction::U, m) => {
+                self.attributes.basis_matrix_u = Some(m)
+            },
+            FreeFormElement::BasisMatrix(Direction::V, m) => {
+                self.attributes.basis_matrix_v = Some(m)
+            },
+            FreeFormElement::Step(u, v) => {
+                self.attributes.step_u = Some(u);
+                self.attributes.step_v = v;
+            },
+            FreeFormElement::Curve(mut c) => {
+                c.attributes = self.attributes.clone();
+                self.curves.push(c);
+                self.open_body = Some(OpenBody::Curve);
+            },
+            FreeFormElement::Curve2D(mut c) => {
+                c.attributes = self.attributes.clone();
+                self.curves_2d.push(c);
+                self.open_body = Some(OpenBody::Curve2D);
+            },
+            FreeFormElement::Surface(mut s) => {
+                s.attributes = self.attributes.clone();
+                self.surfaces.push(s);
+                self.open_body = Some(OpenBody::Surface);
+            },
+            FreeFormElement::Parameter(direction, values) => {
+                let body = self.open_body()?;
+                match direction {
+                    Direction::U => body.parameters_u = values,
+                    Direction::V => body.parameters_v = values,
+                }
+            },
+            FreeFormElement::Trim(loop_) => self.open_body()?.trims.push(loop_),
+            FreeFormElement::Hole(loop_) => self.open_body()?.holes.push(loop_),
+            FreeFormElement::SpecialCurve(c) => self.open_body()?.special_curves.push(c),
+            FreeFormElement::SpecialPoint(p) => self.open_body()?.special_points.extend(p),
+            FreeFormElement::End => {
+                if self.open_body.take().is_none() {
+                    return Err("`end` found without a curve or surface to close".into());
+                }
+            },
+            FreeFormElement::Connection(c) => {
+                for edge in [c.first, c.second] {
+                    if edge.surface_index < 1 || edge.surface_index as usize > self.surfaces.len() {
+                        return Err(format!("Surface {} doesn't exist", edge.surface_index));
+                    }
+                    if edge.curve_index < 1 || edge.curve_index as usize > self.curves_2d.len() {
+                        return Err(format!("Curve {} doesn't exist", edge.curve_index));
+                    }
+                }
+                self.connections.push(c);
+            },
+        }
+        Ok(())
+    }
+
+    fn open_body(&mut self) -> Result<&mut FreeFormBody, String> {
+        let body = match self.open_body {
+            Some(OpenBody::Curve) => self.curves.last_mut().map(|c| &mut c.body),
+            Some(OpenBody::Curve2D) => self.curves_2d.last_mut().map(|c| &mut c.body),
+            Some(OpenBody::Surface) => self.surfaces.last_mut().map(|s| &mut s.body),
+            None => None,
+        };
+        body.ok_or_else(|| "Body statement found outside of a curve or surface".into())
+    }
+}
+
+pub(crate) fn parse_free_form_element<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    alt((
+        parse_curve_surface_type(),
+        parse_degree(),
+        parse_basis_matrix(),
+        parse_step(),
+        parse_curve(),
+        parse_curve_2d(),
+        parse_surface(),
+        parse_parameter(),
+        map(
+            preceded(token_match!(Token::Trim), parse_curve_references()),
+            FreeFormElement::Trim,
+        ),
+        map(
+            preceded(token_match!(Token::Hole), parse_curve_references()),
+            FreeFormElement::Hole,
+        ),
+        map(
+            preceded(token_match!(Token::SpecialCurve), parse_curve_references()),
+            FreeFormElement::SpecialCurve,
+        ),
+        map(
+            preceded(token_match!(Token::SpecialPoint), many1(int())),
+            FreeFormElement::SpecialPoint,
+        ),
+        map(token_match!(Token::End), |_| FreeFormElement::End),
+        parse_connection(),
+    ))
+}
+
+pub(crate) fn parse_curve_surface_type<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(
+            token_match!(Token::CurveSurfaceType),
+            (opt(parse_word("rat")), parse_basis()),
+        ),
+        |(rat, basis)| FreeFormElement::CurveSurfaceType((rat.is_some(), basis).into()),
+    )
+}
+
+pub(crate) fn parse_degree<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(token_match!(Token::Degree), (degree(), opt(degree()))),
+        |(u, v)| FreeFormElement::Degree(u, v),
+    )
+}
+
+pub(crate) fn parse_basis_matrix<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(
+            token_match!(Token::BasisMatrix),
+            (parse_direction(), many1(float())),
+        ),
+        |(direction, matrix)| FreeFormElement::BasisMatrix(direction, matrix),
+    )
+}
+
+pub(crate) fn parse_step<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(token_match!(Token::Step), (float(), opt(float()))),
+        |(u, v)| FreeFormElement::Step(u, v),
+    )
+}
+
+pub(crate) fn parse_curve<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(token_match!(Token::Curve), (float(), float(), many1(int()))),
+        |(start, end, vertices)| {
+            FreeFormElement::Curve(Curve {
+                start,
+                end,
+                vertices,
+                ..Default::default()
+            })
+        },
+    )
+}
+
+pub(crate) fn parse_curve_2d<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(token_match!(Token::Curve2), many1(int())),
+        |param_vertices| {
+            FreeFormElement::Curve2D(Curve2D {
+                param_vertices,
+                ..Default::default()
+            })
+        },
+    )
+}
+
+pub(crate) fn parse_surface<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(
+            token_match!(Token::Surface),
+            (
+                float(),
+                float(),
+                float(),
+                float(),
+                many1(parse_face_element()),
+            ),
+        ),
+        |(start_u, end_u, start_v, end_v, vertices)| {
+            FreeFormElement::Surface(Surface {
+                start_u,
+                end_u,
+                start_v,
+                end_v,
+                vertices,
+                ..Default::default()
+            })
+        },
+    )
+}
+
+pub(crate) fn parse_parameter<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(
+            token_match!(Token::Parameter),
+            (parse_direction(), many1(float())),
+        ),
+        |(direction, values)| FreeFormElement::Parameter(direction, values),
+    )
+}
+
+pub(crate) fn parse_connection<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FreeFormElement, Error = error::Error<TokenSet<'a>>> {
+    let edge = || map((int(), float(), float(), int()), SurfaceEdge::from);
+    map(
+        preceded(token_match!(Token::Connect), (edge(), edge())),
+        |(first, second)| FreeFormElement::Connection(Connection::new(first, second)),
+    )
+}
+
+fn parse_curve_references<'a>(
+) -> impl Parser<TokenSet<'a>, Output = Vec<CurveReference>, Error = error::Error<TokenSet<'a>>> {
+    many1(map((float(), float(), int()), CurveReference::from))
+}
+
+pub(crate) fn parse_curve_technique<'a>(
+) -> impl Parser<TokenSet<'a>, Output = CurveApproximation, Error = error::Error<TokenSet<'a>>> {
+    preceded(
+        token_match!(Token::CurveTechnique),
+        alt((
+            map(preceded(parse_word("cparm"), float()), |resolution| {
+                CurveApproximation::Parametric { resolution }
+            }),
+            map(preceded(parse_word("cspace"), float()), |max_length| {
+                CurveApproximation::Spatial { max_length }
+            }),
+            map(
+                preceded(parse_word("curv"), (float(), float())),
+                |(max_distance, max_angle)| CurveApproximation::Curvature {
+                    max_distance,
+                    max_angle,
+                },
+            ),
+        )),
+    )
+}
+
+pub(crate) fn parse_surface_technique<'a>(
+) -> impl Parser<TokenSet<'a>, Output = SurfaceApproximation, Error = error::Error<TokenSet<'a>>> {
+    preceded(
+        token_match!(Token::SurfaceTechnique),
+        alt((
+            map(
+                preceded(parse_word("cparma"), (float(), float())),
+                |(u_resolution, v_resolution)| SurfaceApproximation::ParametricA {
+                    u_resolution,
+                    v_resolution,
+                },
+            ),
+            map(preceded(parse_word("cparmb"), float()), |resolution| {
+                SurfaceApproximation::ParametricB { resolution }
+            }),
+            map(preceded(parse_word("cspace"), float()), |max_length| {
+                SurfaceApproximation::Spatial { max_length }
+            }),
+            map(
+                preceded(parse_word("curv"), (float(), float())),
+                |(max_distance, max_angle)| SurfaceApproximation::Curvature {
+                    max_distance,
+                    max_angle,
+                },
+            ),
+        )),
+    )
+}
+
+/// Matches a `u` or `v` direction.
+fn parse_direction<'a>(
+) -> impl Parser<TokenSet<'a>, Output = Direction, Error = error::Error<TokenSet<'a>>> {
+    alt((
+        map(parse_word("u"), |_| Direction::U),
+        map(parse_word("v"), |_| Direction::V),
+    ))
+}
+
+fn parse_basis<'a>() -> impl Parser<TokenSet<'a>, Output = Basis, Error = error::Error<TokenSet<'a>>>
+{
+    map_res(token_match!(Token::String(_)), |s| {
+        let basis = get_token_string(&s).map_err(|_| ())?;
+        match basis.to_lowercase().as_ref() {
+            "bmatrix" => Ok(Basis::BasisMatrix),
+            "bezier" => Ok(Basis::Bezier),
+            "bspline" => Ok(Basis::BSpline),
+            "cardinal" => Ok(Basis::Cardinal),
+            "taylor" => Ok(Basis::Taylor),
+            _ => Err(()),
+        }
+    })
+}
+
+/// Matches a string token which is `word`, ignoring case.
+fn parse_word<'a>(
+    word: &'static str,
+) -> impl Parser<TokenSet<'a>, Output = (), Error = error::Error<TokenSet<'a>>> {
+    map_res(token_match!(Token::String(_)), move |s| match s {
+        Token::String(s) if s.eq_ignore_ascii_case(word) => Ok(()),
+        _ => Err(()),
+    })
+}
+
+fn degree<'a>() -> impl Parser<TokenSet<'a>, Output = u32, Error = error::Error<TokenSet<'a>>> {
+    map_res(int(), u32::try_from)
+}
@@ -0,0 +1,218 @@
+use std::{fmt, result::Result};
+
+use thiserror::Error;
+
+use crate::{Face, FaceElement, Line, LineElement, Model, Point};
+
+/// The error produced when an index doesn't refer to any data in a `Model`.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum IndexError {
+    /// Obj indices start at 1, so 0 never refers to anything.
+    #[error("Index 0 doesn't refer to any {kind}, indices start at 1")]
+    Zero { kind: &'static str },
+
+    /// The index is past the end of the data.
+    #[error("Index {index} is out of range, there are {count} {kind}")]
+    OutOfRange {
+        kind: &'static str,
+        index: i32,
+        count: usize,
+    },
+
+    /// Relative indices depend on where they were declared, so they can
+    /// only be resolved while parsing. That is skipped when
+    /// `ParseOptions::resolve_indices` is turned off.
+    #[error("Index {index} into the {kind} is relative and wasn't resolved while parsing")]
+    Relative { kind: &'static str, index: i32 },
+}
+
+/// Where `Model::validate` found a problem. The positions are zero-based.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ElementLocation {
+    /// An element of an entry in `Model::faces`.
+    Face { face: usize, element: usize },
+    /// An element of an entry in `Model::lines`.
+    Line { line: usize, element: usize },
+    /// An element of an entry in `Model::points`.
+    Point { point: usize, element: usize },
+}
+
+impl fmt::Display for ElementLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Face { face, element } => write!(f, "face {}, element {}", face, element),
+            Self::Line { line, element } => write!(f, "line {}, element {}", line, element),
+            Self::Point { point, element } => write!(f, "point {}, element {}", point, element),
+        }
+    }
+}
+
+/// A problem found by `Model::validate`.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The element refers to data which doesn't exist.
+    #[error("Invalid index at {location}: `{error}`")]
+    Index {
+        location: ElementLocation,
+        error: IndexError,
+    },
+
+    /// The element has texture or normal indices where the first element
+    /// of the face or line doesn't, or the other way around.
+    #[error("Element at {location} has a different format than the first element")]
+    MixedFormat { location: ElementLocation },
+}
+
+/// The zero-based positions in `Model::vertices`, `Model::textures` and
+/// `Model::normals` an element of a face, line or point refers to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ElementIndices {
+    /// Position in `Model::vertices`.
+    pub vertex: usize,
+    /// Position in `Model::textures`.
+    pub texture: Option<usize>,
+    /// Position in `Model::normals`.
+    pub normal: Option<usize>,
+}
+
+impl Model {
+    /// Turns a vertex index as written in the obj content into a position
+    /// in `Model::vertices`.
+    pub fn vertex_index(&self, index: i32) -> Result<usize, IndexError> {
+        zero_based("vertices", index, self.vertices.len())
+    }
+
+    /// Turns a texture coordinate index as written in the obj content into
+    /// a position in `Model::textures`.
+    pub fn texture_index(&self, index: i32) -> Result<usize, IndexError> {
+        zero_based("texture coordinates", index, self.textures.len())
+    }
+
+    /// Turns a normal index as written in the obj content into a position
+    /// in `Model::normals`.
+    pub fn normal_index(&self, index: i32) -> Result<usize, IndexError> {
+        zero_based("normals", index, self.normals.len())
+    }
+}
+
+impl FaceElement {
+    /// The zero-based positions of the data the element refers to in
+    /// `model`.
+    pub fn indices(&self, model: &Model) -> Result<ElementIndices, IndexError> {
+        Ok(ElementIndices {
+            vertex: model.vertex_index(self.vertex_index)?,
+            texture: self
+                .texture_index
+                .map(|t| model.texture_index(t))
+                .transpose()?,
+            normal: self
+                .normal_index
+                .map(|n| model.normal_index(n))
+                .transpose()?,
+        })
+    }
+}
+
+impl LineElement {
+    /// The zero-based positions of the data the element refers to in
+    /// `model`.
+    pub fn indices(&self, model: &Model) -> Result<ElementIndices, IndexError> {
+        Ok(ElementIndices {
+            vertex: model.vertex_index(self.vertex_index)?,
+            texture: self
+                .texture_index
+                .map(|t| model.texture_index(t))
+                .transpose()?,
+            normal: None,
+        })
+    }
+}
+
+impl Face {
+    /// The zero-based positions of the data each element of the face refers
+    /// to in `model`.
+    pub fn indices(&self, model: &Model) -> Result<Vec<ElementIndices>, IndexError> {
+        self.elements.iter().map(|e| e.indices(model)).collect()
+    }
+}
+
+impl Line {
+    /// The zero-based positions of the data each element of the line refers
+    /// to in `model`.
+    pub fn indices(&self, model: &Model) -> Result<Vec<ElementIndices>, IndexError> {
+        self.elements.iter().map(|e| e.indices(model)).collect()
+    }
+}
+
+impl Point {
+    /// The zero-based positions in `Model::vertices` of the points.
+    pub fn indices(&self, model: &Model) -> Result<Vec<usize>, IndexError> {
+        self.elements
+            .iter()
+            .map(|v| model.vertex_index(*v))
+            .collect()
+    }
+}
+
+impl Model {
+    /// Checks that every face, line and point only refers to existing
+    /// vertices, texture coordinates and normals, and that the elements of
+    /// each face and line use the same format, such as `v/vt` or `v//vn`.
+    ///
+    /// Every problem found is returned. Relative indices are only reported
+    /// as problems when they were kept by turning off
+    /// `ParseOptions::resolve_indices`.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        for (face, f) in self.faces.iter().enumerate() {
+            let format = |e: &FaceElement| (e.texture_index.is_some(), e.normal_index.is_some());
+            let first = f.elements.first().map(format);
+            for (element, e) in f.elements.iter().enumerate() {
+                let location = ElementLocation::Face { face, element };
+                if let Err(error) = e.indices(self) {
+                    errors.push(ValidationError::Index { location, error });
+                }
+                if Some(format(e)) != first {
+                    errors.push(ValidationError::MixedFormat { location });
+                }
+            }
+        }
+        for (line, l) in self.lines.iter().enumerate() {
+            let first = l.elements.first().map(|e| e.texture_index.is_some());
+            for (element, e) in l.elements.iter().enumerate() {
+                let location = ElementLocation::Line { line, element };
+                if let Err(error) = e.indices(self) {
+                    errors.push(ValidationError::Index { location, error });
+                }
+                if Some(e.texture_index.is_some()) != first {
+                    errors.push(ValidationError::MixedFormat { location });
+                }
+            }
+        }
+        for (point, p) in self.points.iter().enumerate() {
+            for (element, v) in p.elements.iter().enumerate() {
+                if let Err(error) = self.vertex_index(*v) {
+                    let location = ElementLocation::Point { point, element };
+                    errors.push(ValidationError::Index { location, error });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Checks a one-based index against the number of elements, and turns it
+/// into a zero-based one.
+fn zero_based(kind: &'static str, index: i32, count: usize) -> Result<usize, IndexError> {
+    match index {
+        0 => Err(IndexError::Zero { kind }),
+        i if i < 0 => Err(IndexError::Relative { kind, index }),
+        i if i as usize > count => Err(IndexError::OutOfRange { kind, index, count }),
+        i => Ok(i as usize - 1),
+    }
+}
@@ -10,6 +10,21 @@
 //! parse and then return a result with either some kind of parse error, or
 //! a struct containing the data.  
 //!
+//! By default any statement which can't be parsed is an error. The
+//! `load_obj_with_options` and `load_mtl_with_options` variants accept
+//! `ParseOptions`, whose lenient mode skips such statements and reports
+//! them as diagnostics alongside the result instead. Values which had to be
+//! worked around are reported as warnings, and `diagnostics_as_errors` turns
+//! any diagnostic into a hard error.  
+//!
+//! Free-form curves and surfaces are kept in `Model::free_form`. Bezier and
+//! B-spline geometry can be turned into regular lines and faces with
+//! `Model::tessellate`.  
+//!
+//! The parsed model can be prepared for rendering: `Model::transform` converts
+//! between coordinate systems, `Model::generate_normals` fills in missing
+//! normals, and `Model::to_indexed_mesh` builds vertex and index buffers.  
+//!
 //! Note that this crate leaves the responsibility of file I/O to the consuming
 //! application. For example, it's possible to specify file names as attributes
 //! in the material, or file names as material libraries in the obj file. This
@@ -52,12 +67,11 @@
 //!
 //!     let res = nobject_rs::load_obj(&input).unwrap();
 //!     let group = &res.groups["default"];
-//!     let face_group = &res.faces["default"];
 //!     assert_eq!(res.vertices.len(), 8);
 //!     assert_eq!(group.material_name, "Default".to_string());
 //!     assert_eq!(res.normals.len(), 0);
-//!     assert_eq!(res.faces.len(), 1);
-//!     assert_eq!(face_group.len(), 6);;
+//!     assert_eq!(res.faces.len(), 6);
+//!     assert_eq!(res.group_faces("default").count(), 6);
 //! }
 //! ```
 //!
@@ -85,14 +99,27 @@ mod macros;
 mod test;
 mod tokenizer;
 
+mod diagnostic;
+mod free_form;
+mod index;
 mod material;
+mod math;
+mod mesh;
 mod model;
+mod normals;
+mod options;
+mod stats;
+mod tangent;
+mod tessellate;
+mod transform;
+mod triangulate;
 
 use std::borrow::Cow;
 use std::result::Result;
 
 pub use model::{
-    Face, FaceElement, Group, Line, LineElement, Model, ModelError, Normal, Point, Texture, Vertex,
+    Face, FaceElement, Group, Line, LineElement, MaterialRange, Model, ModelError, Normal, Object,
+    ParamVertex, Point, Texture, Vertex,
 };
 
 pub use material::{
@@ -100,8 +127,27 @@ pub use material::{
     NonColorCorrectedMap, ReflectionMap,
 };
 
+pub use diagnostic::{Diagnostic, Diagnostics, Severity};
+pub use free_form::{
+    Basis, Connection, Curve, Curve2D, CurveApproximation, CurveReference, CurveSurfaceType,
+    Direction, FreeForm, FreeFormAttributes, FreeFormBody, Surface, SurfaceApproximation,
+    SurfaceEdge,
+};
+pub use index::{ElementIndices, ElementLocation, IndexError, ValidationError};
+pub use mesh::IndexedMesh;
+pub use options::{GroupMode, ParseMode, ParseOptions};
+pub use stats::{BoundingBox, BoundingSphere, Statistics};
+pub use tangent::TangentError;
+pub use tessellate::{TessellationError, TessellationOptions};
+pub use tokenizer::{Span, TokenizeError};
+pub use transform::{Transform, TransformError};
+pub use triangulate::Triangulation;
+
+pub use indexmap::IndexMap;
+
+use nom::{combinator::map_res, error, IResult, Parser};
 use thiserror::Error;
-use tokenizer::{Token, TokenizeError};
+use tokenizer::{Token, TokenSet};
 
 /// The set of errors which might be generated.
 #[derive(Error, Debug)]
@@ -132,8 +178,27 @@ pub enum ObjError {
     InvalidOnOffValue(String),
 }
 
+impl ObjError {
+    /// The position in the input content the error occurred at.
+    ///
+    /// Errors produced while tokenizing or parsing always have a position.
+    /// The token conversion errors do not, as they aren't tied to a location
+    /// in the input.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Tokenize(e) => Some(e.span()),
+            Self::ModelParse(e) => Some(e.span()),
+            Self::MaterialParse(e) => Some(e.span()),
+            Self::UnexpectedToken(_) | Self::InvalidOnOffValue(_) => None,
+        }
+    }
+}
+
 /// Takes the content of an obj file and parses it.
 ///
+/// This uses the default `ParseOptions`, so any statement which can't be
+/// parsed results in an error.
+///
 /// # Arguments  
 /// * input - The content of the obj file as a string
 ///
@@ -141,14 +206,34 @@ pub enum ObjError {
 /// Returns a `Result` of either ObjError on parse errors
 /// or a constructed `Model`.
 pub fn load_obj(input: &str) -> Result<Model, ObjError> {
+    load_obj_with_options(input, &ParseOptions::default()).map(|(model, _)| model)
+}
+
+/// Takes the content of an obj file and parses it using the given options.
+///
+/// # Arguments  
+/// * input - The content of the obj file as a string
+/// * options - Settings controlling how the content is parsed
+///
+/// # Returns  
+/// Returns a `Result` of either ObjError on parse errors
+/// or a constructed `Model` along with any problems which
+/// were recovered from.
+pub fn load_obj_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(Model, Diagnostics), ObjError> {
     match tokenizer::parse_obj(input) {
-        Ok(tokens) => Ok(model::parse(tokens)?),
+        Ok(tokens) => Ok(model::parse((&tokens).into(), options)?),
         Err(e) => Err(e.into()),
     }
 }
 
 /// Takes the content of an mtl file and parses it.
 ///
+/// This uses the default `ParseOptions`, so any statement which can't be
+/// parsed results in an error.
+///
 /// # Arguments  
 /// * input - The content of the mtl file as a string
 ///
@@ -156,8 +241,25 @@ pub fn load_obj(input: &str) -> Result<Model, ObjError> {
 /// Returns a `Result` of either ObjError on parse errors
 /// or a collection of `Material`.
 pub fn load_mtl(input: &str) -> Result<Vec<Material>, ObjError> {
+    load_mtl_with_options(input, &ParseOptions::default()).map(|(materials, _)| materials)
+}
+
+/// Takes the content of an mtl file and parses it using the given options.
+///
+/// # Arguments  
+/// * input - The content of the mtl file as a string
+/// * options - Settings controlling how the content is parsed
+///
+/// # Returns  
+/// Returns a `Result` of either ObjError on parse errors
+/// or a collection of `Material` along with any problems
+/// which were recovered from.
+pub fn load_mtl_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<Material>, Diagnostics), ObjError> {
     match tokenizer::parse_mtl(input) {
-        Ok(tokens) => Ok(material::parse(tokens)?),
+        Ok(tokens) => Ok(material::parse((&tokens).into(), options)?),
         Err(e) => Err(e.into()),
     }
 }
@@ -172,20 +274,6 @@ fn get_token_float(token: &Token) -> Result<f32, ObjError> {
     }
 }
 
-fn get_opt_token_float_opt(token: &Option<Token>) -> Result<Option<f32>, ObjError> {
-    if let Some(t) = token {
-        if let Token::Float(f) = t {
-            Ok(Some(*f))
-        } else if let Token::Int(i) = t {
-            Ok(Some(*i as f32))
-        } else {
-            Err(ObjError::UnexpectedToken(format!("{:#?}", token)))
-        }
-    } else {
-        Ok(None)
-    }
-}
-
 fn get_token_int(token: &Token) -> Result<i32, ObjError> {
     if let Token::Int(i) = token {
         Ok(*i)
@@ -216,3 +304,27 @@ fn get_on_off_from_str(token: &Token) -> Result<bool, ObjError> {
         Err(ObjError::UnexpectedToken(format!("{:#?}", token)))
     }
 }
+
+/// Matches a number token, as a float.
+fn float<'a>() -> impl Parser<TokenSet<'a>, Output = f32, Error = error::Error<TokenSet<'a>>> {
+    map_res(token_match!(Token::Float(_) | Token::Int(_)), |t| {
+        get_token_float(&t)
+    })
+}
+
+/// Matches an integer token.
+fn int<'a>() -> impl Parser<TokenSet<'a>, Output = i32, Error = error::Error<TokenSet<'a>>> {
+    map_res(token_match!(Token::Int(_)), |t| get_token_int(&t))
+}
+
+/// Matches a string token.
+fn string<'a>() -> impl Parser<TokenSet<'a>, Output = String, Error = error::Error<TokenSet<'a>>> {
+    map_res(token_match!(Token::String(_)), |t| {
+        get_token_string(&t).map(String::from)
+    })
+}
+
+/// Matches an `on` or `off` string token.
+fn on_off<'a>() -> impl Parser<TokenSet<'a>, Output = bool, Error = error::Error<TokenSet<'a>>> {
+    map_res(token_match!(Token::String(_)), |t| get_on_off_from_str(&t))
+}
@@ -7,8 +7,8 @@ macro_rules! token_match {
                         input,
                         nom::error::ErrorKind::Eof,
                     )))
-                } else if matches!(input.as_ref()[0], $($token)*) {
-                    let token = input.as_ref()[0].clone();
+                } else if matches!(input[0], $($token)*) {
+                    let token = input[0].clone();
                     let (_, remainder) = input.split_at(1);
                     Ok((remainder, token))
                 } else {
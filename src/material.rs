@@ -1,12 +1,13 @@
 use std::result::Result;
 
 use crate::{
-    get_on_off_from_str, get_opt_token_float_opt, get_token_float, get_token_int, get_token_string,
-    tokenizer::{Token, TokenSet},
+    float, int, on_off, string,
+    tokenizer::{self, Span, Token, TokenSet},
+    Diagnostic, Diagnostics, ParseOptions, Severity,
 };
 use nom::{
     branch::alt,
-    combinator::{map, opt},
+    combinator::{map, map_res, opt},
     error,
     multi::many1,
     sequence::preceded,
@@ -376,14 +377,23 @@ impl Material {
 /// while parsing the token stream.
 #[derive(Error, Debug)]
 pub enum MaterialError {
-    #[error("Parse Error: `{0}`")]
-    Parse(String),
+    #[error("Parse Error at {span}: `{message}`")]
+    Parse { message: String, span: Span },
 
     /// The specification generally requires a newmtl statement
     /// to come before all other statements. If this error occurs
     /// it's because we also expect a newmtl statement first.
-    #[error("New Material expected, but not found.")]
-    NewMaterial,
+    #[error("New Material expected, but not found at {span}.")]
+    NewMaterial { span: Span },
+}
+
+impl MaterialError {
+    /// The position in the mtl content the error occurred at.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Parse { span, .. } | Self::NewMaterial { span } => *span,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -412,31 +422,55 @@ enum MaterialElement {
     AntiAliasMap(bool),
 }
 
-pub(crate) fn parse(input: TokenSet<'_>) -> Result<Vec<Material>, MaterialError> {
-    let elements: Vec<MaterialElement> = match parse_material_set().parse_complete(input) {
-        Ok((_, x)) => x,
-        Err(e) => return Err(MaterialError::Parse(e.to_string())),
-    };
-
-    let mut res = Vec::new();
-    for e in elements {
-        if let MaterialElement::Name(n) = e {
-            res.push(Material::default());
-            if let Some(l) = res.last_mut() {
-                l.name = n;
-            }
-        } else if let Some(l) = res.last_mut() {
-            l.set_from_material_element(&e);
-        } else {
-            return Err(MaterialError::NewMaterial);
+pub(crate) fn parse(
+    input: TokenSet<'_>,
+    options: &ParseOptions,
+) -> Result<(Vec<Material>, Diagnostics), MaterialError> {
+    let mut res: Vec<Material> = Vec::new();
+    let mut diagnostics = Diagnostics::default();
+    let mut parser = parse_material_element();
+    let mut input = input;
+    while !input.is_empty() {
+        let span = input.position();
+        let (remainder, element) = tokenizer::parse_statement(&mut parser, input);
+        match element {
+            Ok(MaterialElement::Name(n)) => {
+                res.push(Material {
+                    name: n,
+                    ..Default::default()
+                });
+            },
+            Ok(e) => match res.last_mut() {
+                Some(l) => l.set_from_material_element(&e),
+                None => {
+                    let d = Diagnostic::new(
+                        Severity::Error,
+                        "Statement found before any `newmtl` statement".into(),
+                        span,
+                        input[0].keyword(),
+                    );
+                    diagnostics
+                        .record(d, options)
+                        .map_err(|d| MaterialError::NewMaterial { span: d.span })?;
+                },
+            },
+            Err(d) => {
+                diagnostics
+                    .record(d, options)
+                    .map_err(|d| MaterialError::Parse {
+                        message: d.message,
+                        span: d.span,
+                    })?;
+            },
         }
+        input = remainder;
     }
-    Ok(res)
+    Ok((res, diagnostics))
 }
 
-fn parse_material_set<'a>(
-) -> impl Parser<TokenSet<'a>, Output = Vec<MaterialElement>, Error = error::Error<TokenSet<'a>>> {
-    many1(alt((
+fn parse_material_element<'a>(
+) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
+    alt((
         alt((
             parse_new_material(),
             parse_ambient(),
@@ -463,26 +497,14 @@ fn parse_material_set<'a>(
             parse_reflection_map(),
             parse_anti_alias_map(),
         )),
-    )))
+    ))
 }
 
 fn parse_new_material<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::NewMaterial),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let name = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            MaterialElement::Name(name.into())
-        },
+        preceded(token_match!(Token::NewMaterial), string()),
+        MaterialElement::Name,
     )
 }
 
@@ -522,16 +544,7 @@ fn parse_specular_exponent<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::SpecularExponent),
-        map(token_match!(Token::Float(_) | Token::Int(_)), |f| {
-            let f = match get_token_float(&f) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            MaterialElement::SpecularExponent(f)
-        }),
+        map(float(), MaterialElement::SpecularExponent),
     )
 }
 
@@ -540,32 +553,10 @@ fn parse_disolve<'a>(
     preceded(
         token_match!(Token::Disolved),
         alt((
-            map(
-                preceded(
-                    token_match!(Token::Halo),
-                    token_match!(Token::Float(_) | Token::Int(_)),
-                ),
-                |f| {
-                    let f = match get_token_float(&f) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    };
-                    MaterialElement::Disolve(DisolveType::Halo(f))
-                },
-            ),
-            map(token_match!(Token::Float(_) | Token::Int(_)), |f| {
-                let f = match get_token_float(&f) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                };
-                MaterialElement::Disolve(DisolveType::Alpha(f))
+            map(preceded(token_match!(Token::Halo), float()), |f| {
+                MaterialElement::Disolve(DisolveType::Halo(f))
             }),
+            map(float(), |f| MaterialElement::Disolve(DisolveType::Alpha(f))),
         )),
     )
 }
@@ -574,16 +565,7 @@ fn parse_transparency<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::Transparancy),
-        map(token_match!(Token::Float(_) | Token::Int(_)), |f| {
-            let f = match get_token_float(&f) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            MaterialElement::Transparency(f)
-        }),
+        map(float(), MaterialElement::Transparency),
     )
 }
 
@@ -599,16 +581,7 @@ fn parse_sharpness<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::Sharpness),
-        map(token_match!(Token::Float(_) | Token::Int(_)), |f| {
-            let f = match get_token_float(&f) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            MaterialElement::Sharpness(f)
-        }),
+        map(float(), MaterialElement::Sharpness),
     )
 }
 
@@ -616,16 +589,7 @@ fn parse_index_of_refraction<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::IndexOfRefraction),
-        map(token_match!(Token::Float(_) | Token::Int(_)), |f| {
-            let f = match get_token_float(&f) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            MaterialElement::IndexOfRefraction(f)
-        }),
+        map(float(), MaterialElement::IndexOfRefraction),
     )
 }
 
@@ -633,15 +597,8 @@ fn parse_illumination_model<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::IlluminationModel),
-        map(token_match!(Token::Int(_)), |f| {
-            let f = match get_token_int(&f) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            MaterialElement::IlluminationModel(f as u32)
+        map_res(int(), |i| {
+            u32::try_from(i).map(MaterialElement::IlluminationModel)
         }),
     )
 }
@@ -740,16 +697,7 @@ fn parse_anti_alias_map<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = MaterialElement, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::AntiAliasMap),
-        map(token_match!(Token::String(_)), |o| {
-            let val = match get_on_off_from_str(&o) {
-                Ok(v) => v,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            MaterialElement::AntiAliasMap(val)
-        }),
+        map(on_off(), MaterialElement::AntiAliasMap),
     )
 }
 
@@ -767,16 +715,7 @@ fn parse_options<'a>(
         parse_option_texture_resolution(),
         parse_option_imf_channel(),
         parse_option_reflection_type(),
-        map(token_match!(Token::String(_)), |s| {
-            let name = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::FileName(name.into())
-        }),
+        map(string(), OptionElement::FileName),
     )))
 }
 
@@ -784,36 +723,12 @@ fn parse_option_blend<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     alt((
         map(
-            preceded(
-                token_match!(Token::OptionBlendU),
-                token_match!(Token::String(_)),
-            ),
-            |s| {
-                let val = match get_on_off_from_str(&s) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                };
-                OptionElement::BlendU(val)
-            },
+            preceded(token_match!(Token::OptionBlendU), on_off()),
+            OptionElement::BlendU,
         ),
         map(
-            preceded(
-                token_match!(Token::OptionBlendV),
-                token_match!(Token::String(_)),
-            ),
-            |s| {
-                let val = match get_on_off_from_str(&s) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                };
-                OptionElement::BlendV(val)
-            },
+            preceded(token_match!(Token::OptionBlendV), on_off()),
+            OptionElement::BlendV,
         ),
     ))
 }
@@ -821,264 +736,89 @@ fn parse_option_blend<'a>(
 fn parse_option_bm<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionBumpMultiplier),
-            token_match!(Token::Float(_) | Token::Int(_)),
-        ),
-        |s| {
-            let val = match get_token_float(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::BumpMultiplier(val)
-        },
+        preceded(token_match!(Token::OptionBumpMultiplier), float()),
+        OptionElement::BumpMultiplier,
     )
 }
 
 fn parse_option_cc<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionColorCorrect),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let val = match get_on_off_from_str(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::Cc(val)
-        },
+        preceded(token_match!(Token::OptionColorCorrect), on_off()),
+        OptionElement::Cc,
     )
 }
 
 fn parse_option_clamp<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionClamp),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let val = match get_on_off_from_str(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::Clamp(val)
-        },
+        preceded(token_match!(Token::OptionClamp), on_off()),
+        OptionElement::Clamp,
     )
 }
 
 fn parse_option_texture_range<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionRange),
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                token_match!(Token::Float(_) | Token::Int(_)),
-            ),
-        ),
-        |(base, gain)| {
-            let base = match get_token_float(&base) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            let gain = match get_token_float(&gain) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::TextureRange((base, gain))
-        },
+        preceded(token_match!(Token::OptionRange), (float(), float())),
+        OptionElement::TextureRange,
     )
 }
 
 fn parse_option_offset<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionOffset),
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-            ),
-        ),
-        |(x, y, z)| {
-            let x = match get_token_float(&x) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            let y = match get_opt_token_float_opt(&y) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    None
-                },
-            };
-            let z = match get_opt_token_float_opt(&z) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    None
-                },
-            };
-            OptionElement::Offset((x, y, z))
-        },
+        preceded(token_match!(Token::OptionOffset), parse_option_xyz()),
+        OptionElement::Offset,
     )
 }
 
 fn parse_option_scale<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionScale),
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-            ),
-        ),
-        |(x, y, z)| {
-            let x = match get_token_float(&x) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            let y = match get_opt_token_float_opt(&y) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    None
-                },
-            };
-            let z = match get_opt_token_float_opt(&z) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    None
-                },
-            };
-            OptionElement::Scale((x, y, z))
-        },
+        preceded(token_match!(Token::OptionScale), parse_option_xyz()),
+        OptionElement::Scale,
     )
 }
 
 fn parse_option_turbulance<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionTurbulence),
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-            ),
-        ),
-        |(x, y, z)| {
-            let x = match get_token_float(&x) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            let y = match get_opt_token_float_opt(&y) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    None
-                },
-            };
-            let z = match get_opt_token_float_opt(&z) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    None
-                },
-            };
-            OptionElement::Turbulance((x, y, z))
-        },
+        preceded(token_match!(Token::OptionTurbulence), parse_option_xyz()),
+        OptionElement::Turbulance,
     )
 }
 
+/// The `u [v [w]]` values shared by the offset, scale and turbulence options.
+fn parse_option_xyz<'a>() -> impl Parser<
+    TokenSet<'a>,
+    Output = (f32, Option<f32>, Option<f32>),
+    Error = error::Error<TokenSet<'a>>,
+> {
+    (float(), opt(float()), opt(float()))
+}
+
 fn parse_option_texture_resolution<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionTextureResolution),
-            token_match!(Token::Int(_)),
-        ),
-        |s| {
-            let val = match get_token_int(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::TextureRes(val)
-        },
+        preceded(token_match!(Token::OptionTextureResolution), int()),
+        OptionElement::TextureRes,
     )
 }
 
 fn parse_option_imf_channel<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::OptionIMFChan),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let val = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::ImfChan(val.into())
-        },
+        preceded(token_match!(Token::OptionIMFChan), string()),
+        OptionElement::ImfChan,
     )
 }
 
 fn parse_option_reflection_type<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = OptionElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::ReflectionType),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let val = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            OptionElement::ReflectionType(val.into())
-        },
+        preceded(token_match!(Token::ReflectionType), string()),
+        OptionElement::ReflectionType,
     )
 }
 
@@ -1086,101 +826,18 @@ fn parse_color_type<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ColorType, Error = error::Error<TokenSet<'a>>> {
     alt((
         map(
-            (
-                token_match!(Token::Spectral),
-                token_match!(Token::String(_)),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-            ),
-            |(_, file, factor)| {
-                let file_name = match get_token_string(&file) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                };
-                let factor = match get_opt_token_float_opt(&factor) {
-                    Ok(s) => s.unwrap_or(1.0),
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                };
-                ColorType::Spectral(file_name.into(), factor)
-            },
+            preceded(token_match!(Token::Spectral), (string(), opt(float()))),
+            |(file_name, factor)| ColorType::Spectral(file_name, factor.unwrap_or(1.0)),
         ),
         map(
-            (
+            preceded(
                 token_match!(Token::Xyz),
-                token_match!(Token::Float(_) | Token::Int(_)),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-            ),
-            |(_, x_token, y_token, z_token)| {
-                let x = match get_token_float(&x_token) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                };
-                let y = match y_token {
-                    Some(y) => match get_token_float(&y) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    },
-                    None => x,
-                };
-                let z = match z_token {
-                    Some(z) => match get_token_float(&z) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    },
-                    None => x,
-                };
-
-                ColorType::CieXyz(x, y, z)
-            },
-        ),
-        map(
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                token_match!(Token::Float(_) | Token::Int(_)),
-                token_match!(Token::Float(_) | Token::Int(_)),
+                (float(), opt(float()), opt(float())),
             ),
-            |(r, g, b)| {
-                let (r, g, b) = (
-                    match get_token_float(&r) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    },
-                    match get_token_float(&g) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    },
-                    match get_token_float(&b) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    },
-                );
-
-                ColorType::Rgb(r, g, b)
-            },
+            |(x, y, z)| ColorType::CieXyz(x, y.unwrap_or(x), z.unwrap_or(x)),
         ),
+        map((float(), float(), float()), |(r, g, b)| {
+            ColorType::Rgb(r, g, b)
+        }),
     ))
 }
@@ -0,0 +1,47 @@
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn norm(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub(crate) fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    norm(sub(a, b))
+}
+
+pub(crate) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = norm(a);
+    if len > f32::EPSILON {
+        a.map(|x| x / len)
+    } else {
+        a
+    }
+}
+
+/// The normal of the plane which best fits the polygon, using Newell's
+/// method. Its length is twice the area of the polygon.
+pub(crate) fn polygon_normal(points: &[[f32; 3]]) -> [f32; 3] {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .fold([0.0; 3], |n, (a, b)| {
+            [
+                n[0] + (a[1] - b[1]) * (a[2] + b[2]),
+                n[1] + (a[2] - b[2]) * (a[0] + b[0]),
+                n[2] + (a[0] - b[0]) * (a[1] + b[1]),
+            ]
+        })
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, result::Result};
+
+use indexmap::IndexMap;
+
+use crate::{
+    triangulate::triangulate, ElementIndices, ElementLocation, Model, Triangulation,
+    ValidationError,
+};
+
+/// The faces of one group which use one material, as vertex buffers
+/// and a triangle index buffer.
+///
+/// The buffers are stored as separate arrays. Every combination of vertex,
+/// texture coordinate and normal used by the faces becomes one entry in
+/// them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexedMesh {
+    /// The name of the group, as used in `Model::groups`.
+    pub group: String,
+    /// The material of the faces, or `None` for faces declared before any
+    /// `usemtl` statement.
+    pub material_name: Option<String>,
+    /// Vertex positions.
+    pub positions: Vec<[f32; 3]>,
+    /// Texture coordinates, one per position. This is empty when none of
+    /// the faces have texture coordinates, and elements without them get
+    /// `[0.0, 0.0]` otherwise.
+    pub textures: Vec<[f32; 2]>,
+    /// Normals, one per position. This is empty when none of the faces have
+    /// normals, and elements without them get `[0.0, 0.0, 0.0]` otherwise.
+    pub normals: Vec<[f32; 3]>,
+    /// Tangents, one per position, with the handedness of the bitangent in
+    /// the fourth component. This is empty until
+    /// `IndexedMesh::generate_tangents` is called.
+    pub tangents: Vec<[f32; 4]>,
+    /// Positions of the corners of each triangle, three per triangle.
+    pub indices: Vec<u32>,
+}
+
+/// Collects the vertices of a mesh, sharing the ones which are used more
+/// than once.
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<[f32; 3]>,
+    textures: Vec<Option<[f32; 2]>>,
+    normals: Vec<Option<[f32; 3]>>,
+    indices: Vec<u32>,
+    lookup: HashMap<ElementIndices, u32>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, model: &Model, e: ElementIndices) -> u32 {
+        if let Some(i) = self.lookup.get(&e) {
+            return *i;
+        }
+        let v = &model.vertices[e.vertex];
+        self.positions.push([v.x, v.y, v.z]);
+        self.textures.push(e.texture.map(|t| {
+            let t = &model.textures[t];
+            [t.u, t.v.unwrap_or_default()]
+        }));
+        self.normals.push(e.normal.map(|n| {
+            let n = &model.normals[n];
+            [n.x, n.y, n.z]
+        }));
+        let i = (self.positions.len() - 1) as u32;
+        self.lookup.insert(e, i);
+        i
+    }
+
+    fn build(self, group: &str, material_name: Option<&str>) -> IndexedMesh {
+        fn fill<T: Default + Copy>(values: Vec<Option<T>>) -> Vec<T> {
+            if values.iter().all(Option::is_none) {
+                Vec::new()
+            } else {
+                values.into_iter().map(Option::unwrap_or_default).collect()
+            }
+        }
+        IndexedMesh {
+            group: group.into(),
+            material_name: material_name.map(Into::into),
+            positions: self.positions,
+            textures: fill(self.textures),
+            normals: fill(self.normals),
+            tangents: Vec::new(),
+            indices: self.indices,
+        }
+    }
+}
+
+impl Model {
+    /// Builds vertex and index buffers for the faces of the model, with one
+    /// mesh for each material used by each group.
+    ///
+    /// The meshes are ordered by group, then by the first use of each
+    /// material within the group. A face which belongs to several groups is
+    /// part of the mesh of each of them. Faces are split into triangles
+    /// with `Triangulation::EarClipping`, and faces with fewer than three
+    /// elements are skipped. Lines and points aren't included.
+    ///
+    /// An error is returned for the first face which refers to data that
+    /// doesn't exist.
+    pub fn to_indexed_mesh(&self) -> Result<Vec<IndexedMesh>, ValidationError> {
+        let mut res = Vec::new();
+        for (name, group) in &self.groups {
+            let mut builders: IndexMap<Option<&str>, MeshBuilder> = IndexMap::new();
+            // The ranges are in the same order as the faces, so they can be
+            // walked alongside them.
+            let mut ranges = group.material_ranges.iter().peekable();
+            for (position, face) in group.faces.iter().enumerate() {
+                while ranges.next_if(|r| r.faces.end <= position).is_some() {}
+                let material = ranges
+                    .peek()
+                    .filter(|r| r.faces.contains(&position))
+                    .map(|r| r.material_name.as_str());
+                let builder = builders.entry(material).or_default();
+
+                let f = &self.faces[*face];
+                let mut corners = Vec::with_capacity(f.elements.len());
+                for (element, e) in f.elements.iter().enumerate() {
+                    let indices = e.indices(self).map_err(|error| ValidationError::Index {
+                        location: ElementLocation::Face {
+                            face: *face,
+                            element,
+                        },
+                        error,
+                    })?;
+                    corners.push(indices);
+                }
+                if corners.len() < 3 {
+                    continue;
+                }
+                let points = corners
+                    .iter()
+                    .map(|e| {
+                        let v = &self.vertices[e.vertex];
+                        [v.x, v.y, v.z]
+                    })
+                    .collect::<Vec<_>>();
+                let corners = corners
+                    .into_iter()
+                    .map(|e| builder.vertex(self, e))
+                    .collect::<Vec<_>>();
+                for triangle in triangulate(&points, Triangulation::EarClipping) {
+                    builder.indices.extend(triangle.map(|i| corners[i]));
+                }
+            }
+            res.extend(
+                builders
+                    .into_iter()
+                    .filter(|(_, b)| !b.indices.is_empty())
+                    .map(|(material, b)| b.build(name, material)),
+            );
+        }
+        Ok(res)
+    }
+}
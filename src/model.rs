@@ -1,17 +1,25 @@
-use std::{collections::HashMap, result::Result};
+use std::{ops::Range, result::Result};
 
 use derive_more::{Constructor, From, Into};
+use indexmap::IndexMap;
 
 use crate::{
-    get_on_off_from_str, get_token_float, get_token_int, get_token_string,
-    tokenizer::{Token, TokenSet},
+    float,
+    free_form::{
+        parse_curve_technique, parse_free_form_element, parse_surface_technique, FreeForm,
+        FreeFormElement,
+    },
+    get_token_string, int, on_off, string,
+    tokenizer::{self, Span, Token, TokenSet},
+    CurveApproximation, Diagnostic, Diagnostics, GroupMode, ParseOptions, Severity,
+    SurfaceApproximation,
 };
 
 use nom::{
     branch::alt,
-    combinator::{map, opt},
+    combinator::{map, map_res, opt},
     error,
-    multi::{fold_many0, fold_many1, many1},
+    multi::{fold_many1, many0, many1},
     sequence::preceded,
     IResult, Parser,
 };
@@ -21,8 +29,17 @@ use thiserror::Error;
 /// while parsing the token stream.
 #[derive(Error, Debug)]
 pub enum ModelError {
-    #[error("Parse Error: `{0}`")]
-    Parse(String),
+    #[error("Parse Error at {span}: `{message}`")]
+    Parse { message: String, span: Span },
+}
+
+impl ModelError {
+    /// The position in the obj content the error occurred at.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Parse { span, .. } => *span,
+        }
+    }
 }
 
 /// Representation of vertex data. The w component is optional.
@@ -60,10 +77,30 @@ pub struct Texture {
     pub w: Option<f32>,
 }
 
+/// Representation of a parameter space vertex, used by free-form
+/// curves and surfaces. v/w are optional.
+#[derive(Copy, Clone, Constructor, Debug, Default, From, Into, PartialEq)]
+pub struct ParamVertex {
+    /// U coordinate
+    pub u: f32,
+    /// Optional V coordinate
+    pub v: Option<f32>,
+    /// Optional W coordinate
+    pub w: Option<f32>,
+}
+
 /// Defines the settings that get applied to a group of faces.
-#[derive(Clone, Constructor, Debug, Default, From, Into, PartialEq)]
+///
+/// The material and render attributes, from `material_name` to
+/// `surface_technique`, are the ones in effect the last time the group was
+/// current.
+#[derive(Clone, Debug, Default, From, Into, PartialEq)]
 pub struct Group {
-    /// The name of the material to apply to the group.
+    /// The name of the group, as given in the `g` statement.
+    pub name: String,
+    /// The name of the material in effect the last time the group was
+    /// current, or an empty string if there was none. `material_ranges`
+    /// has the material of each element.
     pub material_name: String,
     /// Bevel interpolation setting.
     pub bevel: bool,
@@ -73,11 +110,57 @@ pub struct Group {
     pub d_interp: bool,
     /// Level of detail setting.
     pub lod: u8,
+    /// Curve approximation technique, used when tessellating curves.
+    pub curve_technique: Option<CurveApproximation>,
+    /// Surface approximation technique, used when tessellating surfaces.
+    pub surface_technique: Option<SurfaceApproximation>,
     /// The name of the texture map file.
     pub texture_map: Option<String>,
+    /// The materials used by the elements of the group, in the order
+    /// they were declared. Elements declared before any `usemtl`
+    /// statement aren't part of any range.
+    pub material_ranges: Vec<MaterialRange>,
+    /// The positions in `Model::faces` of the faces in the group.
+    pub faces: Vec<usize>,
+    /// The positions in `Model::lines` of the lines in the group.
+    pub lines: Vec<usize>,
+    /// The positions in `Model::points` of the points in the group.
+    pub points: Vec<usize>,
+}
+
+impl Group {
+    /// The number of faces, lines and points in the group.
+    fn counts(&self) -> (usize, usize, usize) {
+        (self.faces.len(), self.lines.len(), self.points.len())
+    }
+}
+
+/// A run of consecutive elements of a group which use the same material.
+/// The ranges index into the group's `faces`, `lines` and `points`.
+#[derive(Clone, Constructor, Debug, Default, PartialEq)]
+pub struct MaterialRange {
+    /// The name of the material.
+    pub material_name: String,
+    /// The faces which use the material.
+    pub faces: Range<usize>,
+    /// The lines which use the material.
+    pub lines: Range<usize>,
+    /// The points which use the material.
+    pub points: Range<usize>,
+}
+
+impl MaterialRange {
+    /// The ends of the face, line and point ranges.
+    fn ends(&self) -> (usize, usize, usize) {
+        (self.faces.end, self.lines.end, self.points.end)
+    }
 }
 
 /// Holds the vertex/texture/normal indicies for a part of a face.
+/// Negative indices count back from the data declared before the
+/// statement. They are resolved while parsing, unless
+/// `ParseOptions::resolve_indices` is turned off.
+/// `indices` turns them into checked, zero-based positions.
 #[derive(Copy, Clone, Constructor, Debug, Default, From, Into, PartialEq)]
 pub struct FaceElement {
     /// Vertex index. Note that these START at 1, NOT 0.
@@ -98,9 +181,15 @@ pub struct Face {
     pub elements: Vec<FaceElement>,
     /// The smoothing group identifier.
     pub smoothing_group: i32,
+    /// The merging group identifier. This is 0 when merging is off.
+    pub merging_group: i32,
 }
 
 /// Contains the indicies for a line element.
+/// Negative indices count back from the data declared before the
+/// statement. They are resolved while parsing, unless
+/// `ParseOptions::resolve_indices` is turned off.
+/// `indices` turns them into checked, zero-based positions.
 #[derive(Copy, Clone, Constructor, Debug, Default, From, Into, PartialEq)]
 pub struct LineElement {
     /// Vertex index. Note that these START at 1, NOT 0.
@@ -117,12 +206,44 @@ pub struct Line {
 }
 
 /// Contains a set of id's for the verticies which compose the point collection.
+/// Negative indices count back from the data declared before the
+/// statement. They are resolved while parsing, unless
+/// `ParseOptions::resolve_indices` is turned off.
+/// `indices` turns them into checked, zero-based positions.
 #[derive(Clone, Constructor, Debug, Default, From, Into, PartialEq)]
 pub struct Point {
     /// Set of vertex indices. Note that these START at 1, NOT 0.
     pub elements: Vec<i32>,
 }
 
+/// A named object, as started by an `o` statement, along with the
+/// elements declared until the next `o` statement.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Object {
+    /// The name of the object.
+    pub name: String,
+    /// The names of the groups used by the object, in the order
+    /// they were first used.
+    pub groups: Vec<String>,
+    /// The positions in `Model::faces` of the faces in the object.
+    pub faces: Vec<usize>,
+    /// The positions in `Model::lines` of the lines in the object.
+    pub lines: Vec<usize>,
+    /// The positions in `Model::points` of the points in the object.
+    pub points: Vec<usize>,
+}
+
+impl Object {
+    /// Records that `groups` are used by the object.
+    fn use_groups(&mut self, groups: &[String]) {
+        for g in groups {
+            if !self.groups.contains(g) {
+                self.groups.push(g.clone());
+            }
+        }
+    }
+}
+
 /// This holds the end result of parsing an obj file.
 /// The default group for all models is "default".
 /// That is to say, if no group is defined in a file,
@@ -130,6 +251,10 @@ pub struct Point {
 ///
 /// Everything will fall under the "default" group until another group
 /// is specified.
+///
+/// Faces, lines and points are stored once, and each group lists the
+/// ones which belong to it. `groups` iterates in the order the groups
+/// were declared.
 #[derive(Clone, Debug, From, Into)]
 pub struct Model {
     /// Collection of vertex data
@@ -138,22 +263,20 @@ pub struct Model {
     pub normals: Vec<Normal>,
     /// Collection of texture coordinate data
     pub textures: Vec<Texture>,
-    /// A map of group name to a collection of faces which belong to the group
-    /// Everything will fall under the "default" group until another group
-    /// is specified.
-    pub faces: HashMap<String, Vec<Face>>,
-    /// A map of group name to a collection of lines.
-    /// Everything will fall under the "default" group until another group
-    /// is specified.
-    pub lines: HashMap<String, Vec<Line>>,
-    /// A map of group name to a collection of points.
-    /// Everything will fall under the "default" group until another group
-    /// is specified.
-    pub points: HashMap<String, Vec<Point>>,
+    /// Collection of parameter space vertex data
+    pub param_vertices: Vec<ParamVertex>,
+    /// Collection of faces, in the order they were declared. Groups and
+    /// objects refer to them by position.
+    pub faces: Vec<Face>,
+    /// Collection of lines, in the order they were declared.
+    pub lines: Vec<Line>,
+    /// Collection of points, in the order they were declared.
+    pub points: Vec<Point>,
     /// A map of group name to the groups specific data.
     /// Everything will fall under the "default" group until another group
-    /// is specified.
-    pub groups: HashMap<String, Group>,
+    /// is specified. How groups which are named again are stored depends
+    /// on `ParseOptions::group_mode`.
+    pub groups: IndexMap<String, Group>,
     /// The material library files to use with this obj.
     pub material_libs: Vec<String>,
     /// The texture library files to use with this obj.
@@ -162,9 +285,25 @@ pub struct Model {
     pub shadow_obj: Option<String>,
     /// The file name for the ray trace object
     pub trace_obj: Option<String>,
+    /// Free-form curves and surfaces.
+    pub free_form: FreeForm,
+    /// The resolution of each merging group, by group number. This is the
+    /// maximum distance between surfaces which get merged together.
+    pub merging_groups: IndexMap<i32, f32>,
+    /// The objects declared by `o` statements, in the order they were
+    /// declared. An object refers to its faces, lines and points by their
+    /// positions in `faces`, `lines` and `points`. Elements declared before
+    /// the first `o` statement don't belong to any object.
+    pub objects: Vec<Object>,
 
     current_group: Vec<String>,
     current_smoothing_group: i32,
+    current_merging_group: i32,
+    current_material: Option<String>,
+    /// The render attributes of the current groups. These are the values
+    /// of the last `bevel`, `c_interp`, `d_interp`, `lod`, `ctech` and
+    /// `stech` statements.
+    current_attributes: Group,
 }
 
 impl Default for Model {
@@ -173,20 +312,33 @@ impl Default for Model {
             vertices: Default::default(),
             normals: Default::default(),
             textures: Default::default(),
+            param_vertices: Default::default(),
             faces: Default::default(),
             lines: Default::default(),
             points: Default::default(),
             groups: {
-                let mut res = HashMap::new();
-                res.insert("default".into(), Default::default());
+                let mut res = IndexMap::new();
+                res.insert(
+                    "default".into(),
+                    Group {
+                        name: "default".into(),
+                        ..Default::default()
+                    },
+                );
                 res
             },
             material_libs: Default::default(),
             texture_libs: Default::default(),
             shadow_obj: Default::default(),
             trace_obj: Default::default(),
+            free_form: Default::default(),
+            merging_groups: Default::default(),
+            objects: Default::default(),
             current_group: vec!["default".into()],
             current_smoothing_group: 0,
+            current_merging_group: 0,
+            current_material: None,
+            current_attributes: Default::default(),
         }
     }
 }
@@ -196,6 +348,7 @@ pub(crate) enum ModelElement {
     Vertex(Vertex),
     Normal(Normal),
     Texture(Texture),
+    ParamVertex(ParamVertex),
     Face(Face),
     Line(Line),
     Point(Point),
@@ -204,105 +357,466 @@ pub(crate) enum ModelElement {
     Material(String),
     ObjName(String),
     Smoothing(i32),
+    MergingGroup(i32, Option<f32>),
     Bevel(bool),
     CInterp(bool),
     DInterp(bool),
-    Lod(i32),
+    Lod(u8),
+    CurveTechnique(CurveApproximation),
+    SurfaceTechnique(SurfaceApproximation),
     ShadowObj(String),
     TraceObj(String),
     TextureLib(Vec<String>),
     TextureMap(String),
+    FreeForm(Box<FreeFormElement>),
+    /// A statement which was parsed by working around a problem with it,
+    /// along with a description of the problem.
+    Recovered(Box<ModelElement>, String),
+}
+
+pub(crate) fn parse(
+    input: TokenSet,
+    options: &ParseOptions,
+) -> Result<(Model, Diagnostics), ModelError> {
+    let mut model = Model::default();
+    let mut diagnostics = Diagnostics::default();
+    let mut parser = parse_element();
+    let mut input = input;
+    while !input.is_empty() {
+        let span = input.position();
+        let (remainder, res) = tokenizer::parse_statement(&mut parser, input);
+        let statement = input[0].keyword();
+        let diagnostic = match res {
+            Ok(ModelElement::Recovered(element, message)) => {
+                Some(match model.apply(*element, options) {
+                    Ok(()) => Diagnostic::new(Severity::Warning, message, span, statement),
+                    Err(message) => Diagnostic::new(Severity::Error, message, span, statement),
+                })
+            },
+            Ok(element) => model
+                .apply(element, options)
+                .err()
+                .map(|message| Diagnostic::new(Severity::Error, message, span, statement)),
+            Err(d) => Some(d),
+        };
+        if let Some(d) = diagnostic {
+            diagnostics
+                .record(d, options)
+                .map_err(|d| ModelError::Parse {
+                    message: d.message,
+                    span: d.span,
+                })?;
+        }
+        input = remainder;
+    }
+    Ok((model, diagnostics))
 }
 
-pub(crate) fn parse(input: TokenSet) -> Result<Model, ModelError> {
-    match fold_many0(
+fn parse_element<'a>(
+) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
+    alt((
+        map(parse_vertex(), ModelElement::Vertex),
+        map(parse_vertex_normal(), ModelElement::Normal),
+        map(parse_vertex_texture(), ModelElement::Texture),
+        map(parse_vertex_param(), ModelElement::ParamVertex),
+        map(parse_face(), ModelElement::Face),
+        map(parse_line(), ModelElement::Line),
+        map(parse_point(), ModelElement::Point),
+        parse_mat_lib(),
+        parse_material(),
+        parse_obj_name(),
+        parse_smoothing(),
+        parse_merging_group(),
         alt((
-            map(parse_vertex(), ModelElement::Vertex),
-            map(parse_vertex_normal(), ModelElement::Normal),
-            map(parse_vertex_texture(), ModelElement::Texture),
-            map(parse_face(), ModelElement::Face),
-            map(parse_line(), ModelElement::Line),
-            map(parse_point(), ModelElement::Point),
-            parse_mat_lib(),
-            parse_material(),
-            parse_obj_name(),
-            parse_smoothing(),
             parse_bevel(),
             parse_c_interp(),
             parse_d_interp(),
             parse_lod(),
+            map(parse_curve_technique(), ModelElement::CurveTechnique),
+            map(parse_surface_technique(), ModelElement::SurfaceTechnique),
             parse_shadow_obj(),
             parse_trace_obj(),
-            parse_texture_lib(),
-            parse_texture_map(),
-            parse_group(),
         )),
-        Model::default,
-        |mut model: Model, item: ModelElement| {
-            match item {
-                ModelElement::Vertex(x) => model.vertices.push(x),
-                ModelElement::Normal(n) => model.normals.push(n),
-                ModelElement::Texture(t) => model.textures.push(t),
-                ModelElement::Face(mut f) => {
-                    f.smoothing_group = model.current_smoothing_group;
-                    for g in &model.current_group {
-                        let set = model.faces.entry(g.clone()).or_default();
-                        set.push(f.clone());
+        parse_texture_lib(),
+        parse_texture_map(),
+        parse_group(),
+        map(parse_free_form_element(), |e| {
+            ModelElement::FreeForm(Box::new(e))
+        }),
+    ))
+}
+
+impl Model {
+    fn apply(&mut self, element: ModelElement, options: &ParseOptions) -> Result<(), String> {
+        match element {
+            ModelElement::Vertex(x) => self.vertices.push(x),
+            ModelElement::Normal(n) => self.normals.push(n),
+            ModelElement::Texture(t) => self.textures.push(t),
+            ModelElement::ParamVertex(p) => self.param_vertices.push(p),
+            ModelElement::Face(mut f) => {
+                if options.resolve_indices {
+                    for e in &mut f.elements {
+                        self.resolve_element(
+                            &mut e.vertex_index,
+                            &mut e.texture_index,
+                            &mut e.normal_index,
+                        )?;
                     }
-                },
-                ModelElement::Line(l) => {
-                    for g in &model.current_group {
-                        let set = model.lines.entry(g.clone()).or_default();
-                        set.push(l.clone());
+                }
+                f.smoothing_group = self.current_smoothing_group;
+                f.merging_group = self.current_merging_group;
+                self.insert_current(GroupedElement::Face(f));
+            },
+            ModelElement::Line(mut l) => {
+                if options.resolve_indices {
+                    for e in &mut l.elements {
+                        self.resolve_element(&mut e.vertex_index, &mut e.texture_index, &mut None)?;
                     }
-                },
-                ModelElement::Point(p) => {
-                    for g in &model.current_group {
-                        let set = model.points.entry(g.clone()).or_default();
-                        set.push(p.clone());
+                }
+                self.insert_current(GroupedElement::Line(l))
+            },
+            ModelElement::Point(mut p) => {
+                if options.resolve_indices {
+                    for v in &mut p.elements {
+                        self.resolve_element(v, &mut None, &mut None)?;
                     }
-                },
-                ModelElement::Group(groups) => {
-                    model.current_group.clear();
-                    for g in groups {
-                        model.groups.insert(g.clone(), Default::default());
-                        model.current_group.push(g);
+                }
+                self.insert_current(GroupedElement::Point(p))
+            },
+            ModelElement::Group(groups) => {
+                self.current_group.clear();
+                for g in groups {
+                    let key = match options.group_mode {
+                        GroupMode::Merge => g.clone(),
+                        GroupMode::Split => (1..)
+                            .map(|i| match i {
+                                1 => g.clone(),
+                                _ => format!("{}#{}", g, i),
+                            })
+                            .find(|k| !self.groups.contains_key(k))
+                            .unwrap_or_default(),
+                    };
+                    if !self.groups.contains_key(&key) {
+                        let group = Group {
+                            name: g,
+                            ..Default::default()
+                        };
+                        self.groups.insert(key.clone(), group);
                     }
-                },
-                ModelElement::MaterialLib(libs) => model.material_libs.extend(libs),
-                ModelElement::Material(name) => {
-                    for g in &model.current_group {
-                        let group = model.groups.entry(g.clone()).or_default();
-                        group.material_name = name.clone();
+                    if !self.current_group.contains(&key) {
+                        self.current_group.push(key);
                     }
-                },
-                ModelElement::ObjName(_name) => {},
-                ModelElement::Smoothing(group_id) => {
-                    model.current_smoothing_group = group_id;
-                },
-                ModelElement::Bevel(_flag) => {},
-                ModelElement::CInterp(_flag) => {},
-                ModelElement::DInterp(_flag) => {},
-                ModelElement::Lod(_level) => {},
-                ModelElement::ShadowObj(_name) => {},
-                ModelElement::TraceObj(_name) => {},
-                ModelElement::TextureLib(libs) => {
-                    model.texture_libs.extend(libs);
-                },
-                ModelElement::TextureMap(name) => {
-                    for g in &model.current_group {
-                        let group = model.groups.entry(g.clone()).or_default();
-                        group.texture_map = Some(name.clone());
+                }
+                // Render attributes stay in effect until they are changed,
+                // so a group which is used again picks up the ones set while
+                // it wasn't current.
+                let attributes = std::mem::take(&mut self.current_attributes);
+                self.update_groups(|group| {
+                    group.bevel = attributes.bevel;
+                    group.c_interp = attributes.c_interp;
+                    group.d_interp = attributes.d_interp;
+                    group.lod = attributes.lod;
+                    group.curve_technique = attributes.curve_technique;
+                    group.surface_technique = attributes.surface_technique;
+                });
+                self.current_attributes = attributes;
+                if let Some(material) = self.current_material.clone() {
+                    self.update_groups(|group| group.material_name = material.clone());
+                }
+            },
+            ModelElement::MaterialLib(libs) => self.material_libs.extend(libs),
+            ModelElement::Material(name) => {
+                self.update_groups(|group| group.material_name = name.clone());
+                self.current_material = Some(name);
+            },
+            ModelElement::ObjName(name) => self.objects.push(Object {
+                name,
+                ..Default::default()
+            }),
+            ModelElement::Smoothing(group_id) => {
+                self.current_smoothing_group = group_id;
+            },
+            ModelElement::MergingGroup(group_id, resolution) => {
+                if let Some(resolution) = resolution {
+                    if resolution <= 0.0 {
+                        return Err(format!(
+                            "The resolution of merging group {} has to be greater than 0",
+                            group_id
+                        ));
                     }
+                    if group_id != 0 {
+                        self.merging_groups.insert(group_id, resolution);
+                    }
+                } else if group_id != 0 && !self.merging_groups.contains_key(&group_id) {
+                    return Err(format!("Merging group {} needs a resolution", group_id));
+                }
+                self.current_merging_group = group_id;
+            },
+            ModelElement::Bevel(flag) => {
+                self.current_attributes.bevel = flag;
+                self.update_groups(|group| group.bevel = flag);
+            },
+            ModelElement::CInterp(flag) => {
+                self.current_attributes.c_interp = flag;
+                self.update_groups(|group| group.c_interp = flag);
+            },
+            ModelElement::DInterp(flag) => {
+                self.current_attributes.d_interp = flag;
+                self.update_groups(|group| group.d_interp = flag);
+            },
+            ModelElement::Lod(level) => {
+                if level > 100 {
+                    return Err(format!(
+                        "Level of detail has to be between 0 and 100, found {}",
+                        level
+                    ));
+                }
+                self.current_attributes.lod = level;
+                self.update_groups(|group| group.lod = level);
+            },
+            ModelElement::CurveTechnique(technique) => {
+                self.current_attributes.curve_technique = Some(technique);
+                self.update_groups(|group| group.curve_technique = Some(technique));
+            },
+            ModelElement::SurfaceTechnique(technique) => {
+                self.current_attributes.surface_technique = Some(technique);
+                self.update_groups(|group| group.surface_technique = Some(technique));
+            },
+            ModelElement::ShadowObj(name) => self.shadow_obj = Some(name),
+            ModelElement::TraceObj(name) => self.trace_obj = Some(name),
+            ModelElement::TextureLib(libs) => {
+                self.texture_libs.extend(libs);
+            },
+            ModelElement::TextureMap(name) => {
+                self.update_groups(|group| group.texture_map = Some(name.clone()));
+            },
+            ModelElement::FreeForm(element) => {
+                let element = self.resolve_free_form(*element)?;
+                self.free_form.apply(element)?
+            },
+            ModelElement::Recovered(element, _) => self.apply(*element, options)?,
+        }
+        Ok(())
+    }
+
+    /// Adds `element` to the current groups and object, using the
+    /// current material.
+    fn insert_current(&mut self, element: GroupedElement) {
+        let groups = std::mem::take(&mut self.current_group);
+        let material = self.current_material.take();
+        let object = self.objects.len().checked_sub(1);
+        self.insert(element, &groups, object, material.as_deref());
+        self.current_group = groups;
+        self.current_material = material;
+    }
+
+    /// Adds `element` to each of `groups` and to the object at `object`,
+    /// extending the material ranges of the groups with `material`.
+    pub(crate) fn insert(
+        &mut self,
+        element: GroupedElement,
+        groups: &[String],
+        object: Option<usize>,
+        material: Option<&str>,
+    ) {
+        let (index, kind) = match element {
+            GroupedElement::Face(f) => {
+                self.faces.push(f);
+                (self.faces.len() - 1, ElementKind::Face)
+            },
+            GroupedElement::Line(l) => {
+                self.lines.push(l);
+                (self.lines.len() - 1, ElementKind::Line)
+            },
+            GroupedElement::Point(p) => {
+                self.points.push(p);
+                (self.points.len() - 1, ElementKind::Point)
+            },
+        };
+
+        for g in groups {
+            let group = self.groups.entry(g.clone()).or_default();
+            let before = group.counts();
+            kind.members(&mut group.faces, &mut group.lines, &mut group.points)
+                .push(index);
+            let after = group.counts();
+            let Some(material) = material else {
+                continue;
+            };
+            match group.material_ranges.last_mut() {
+                Some(r) if r.material_name == material && r.ends() == before => {
+                    r.faces.end = after.0;
+                    r.lines.end = after.1;
+                    r.points.end = after.2;
                 },
+                _ => group.material_ranges.push(MaterialRange::new(
+                    material.into(),
+                    before.0..after.0,
+                    before.1..after.1,
+                    before.2..after.2,
+                )),
             }
-            model
-        },
-    )
-    .parse_complete(input)
-    {
-        Ok((_, acc)) => Ok(acc),
-        Err(e) => Err(ModelError::Parse(e.to_string())),
+        }
+
+        if let Some(o) = object.and_then(|o| self.objects.get_mut(o)) {
+            o.use_groups(groups);
+            kind.members(&mut o.faces, &mut o.lines, &mut o.points)
+                .push(index);
+        }
+    }
+
+    /// The faces in the group named `name`, in the order they were
+    /// declared. Nothing is returned if there is no such group.
+    pub fn group_faces<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Face> + 'a {
+        let indices = self.groups.get(name).map(|g| g.faces.as_slice());
+        indices.unwrap_or_default().iter().map(|i| &self.faces[*i])
+    }
+
+    /// The lines in the group named `name`, in the order they were
+    /// declared. Nothing is returned if there is no such group.
+    pub fn group_lines<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Line> + 'a {
+        let indices = self.groups.get(name).map(|g| g.lines.as_slice());
+        indices.unwrap_or_default().iter().map(|i| &self.lines[*i])
+    }
+
+    /// The points in the group named `name`, in the order they were
+    /// declared. Nothing is returned if there is no such group.
+    pub fn group_points<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Point> + 'a {
+        let indices = self.groups.get(name).map(|g| g.points.as_slice());
+        indices.unwrap_or_default().iter().map(|i| &self.points[*i])
+    }
+
+    /// Applies `f` to each of the current groups.
+    fn update_groups(&mut self, f: impl Fn(&mut Group)) {
+        for g in &self.current_group {
+            f(self.groups.entry(g.clone()).or_default());
+        }
+    }
+
+    /// Resolves the relative vertex, texture and normal indices of an
+    /// element of a face, line or point.
+    fn resolve_element(
+        &self,
+        vertex: &mut i32,
+        texture: &mut Option<i32>,
+        normal: &mut Option<i32>,
+    ) -> Result<(), String> {
+        *vertex = resolve_index(*vertex, self.vertices.len())?;
+        *texture = texture
+            .map(|t| resolve_index(t, self.textures.len()))
+            .transpose()?;
+        *normal = normal
+            .map(|n| resolve_index(n, self.normals.len()))
+            .transpose()?;
+        Ok(())
+    }
+
+    /// Resolves the relative indices of a free-form statement, which would
+    /// be ambiguous once the rest of the file has been parsed, and records
+    /// the state the statement was declared in.
+    fn resolve_free_form(&self, mut element: FreeFormElement) -> Result<FreeFormElement, String> {
+        let vertices = self.vertices.len();
+        let param_vertices = self.param_vertices.len();
+        match &mut element {
+            FreeFormElement::Curve(c) => {
+                for v in &mut c.vertices {
+                    *v = resolve_index(*v, vertices)?;
+                }
+                c.groups = self.current_group.clone();
+                c.object = self.objects.len().checked_sub(1);
+                c.material_name = self.current_material.clone();
+                c.technique = self.current_attributes.curve_technique;
+            },
+            FreeFormElement::Curve2D(c) => {
+                for vp in &mut c.param_vertices {
+                    *vp = resolve_index(*vp, param_vertices)?;
+                }
+                c.technique = self.current_attributes.curve_technique;
+            },
+            FreeFormElement::Surface(s) => {
+                for e in &mut s.vertices {
+                    self.resolve_element(
+                        &mut e.vertex_index,
+                        &mut e.texture_index,
+                        &mut e.normal_index,
+                    )?;
+                }
+                s.groups = self.current_group.clone();
+                s.smoothing_group = self.current_smoothing_group;
+                s.merging_group = self.current_merging_group;
+                s.object = self.objects.len().checked_sub(1);
+                s.material_name = self.current_material.clone();
+                s.technique = self.current_attributes.surface_technique;
+            },
+            FreeFormElement::Trim(r)
+            | FreeFormElement::Hole(r)
+            | FreeFormElement::SpecialCurve(r) => {
+                for c in r {
+                    c.curve_index = resolve_index(c.curve_index, self.free_form.curves_2d.len())?;
+                }
+            },
+            FreeFormElement::Connection(c) => {
+                for edge in [&mut c.first, &mut c.second] {
+                    edge.surface_index =
+                        resolve_index(edge.surface_index, self.free_form.surfaces.len())?;
+                    edge.curve_index =
+                        resolve_index(edge.curve_index, self.free_form.curves_2d.len())?;
+                }
+            },
+            FreeFormElement::SpecialPoint(p) => {
+                for vp in p {
+                    *vp = resolve_index(*vp, param_vertices)?;
+                }
+            },
+            _ => {},
+        }
+        Ok(element)
+    }
+}
+
+/// An element which belongs to groups.
+pub(crate) enum GroupedElement {
+    Face(Face),
+    Line(Line),
+    Point(Point),
+}
+
+/// The kind of a `GroupedElement`.
+#[derive(Clone, Copy)]
+enum ElementKind {
+    Face,
+    Line,
+    Point,
+}
+
+impl ElementKind {
+    /// Picks the list of element positions for this kind.
+    fn members<'a>(
+        self,
+        faces: &'a mut Vec<usize>,
+        lines: &'a mut Vec<usize>,
+        points: &'a mut Vec<usize>,
+    ) -> &'a mut Vec<usize> {
+        match self {
+            Self::Face => faces,
+            Self::Line => lines,
+            Self::Point => points,
+        }
+    }
+}
+
+/// Turns a relative (negative) index into an absolute one, given the number
+/// of elements declared so far. Absolute indices are returned unchanged.
+fn resolve_index(index: i32, count: usize) -> Result<i32, String> {
+    if index >= 0 {
+        return Ok(index);
+    }
+    match i32::try_from(count as i64 + 1 + index as i64) {
+        Ok(i) if i >= 1 => Ok(i),
+        _ => Err(format!(
+            "Relative index {} refers to before the first element",
+            index
+        )),
     }
 }
 
@@ -311,46 +825,9 @@ pub(crate) fn parse_vertex<'a>(
     map(
         preceded(
             token_match!(Token::Vertex),
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                token_match!(Token::Float(_) | Token::Int(_)),
-                token_match!(Token::Float(_) | Token::Int(_)),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-            ),
+            (float(), float(), float(), opt(float())),
         ),
-        |(x, y, z, w)| {
-            let (x, y, z) = (
-                match get_token_float(&x) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
-                match get_token_float(&y) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
-                match get_token_float(&z) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
-            );
-            let w = w.map(|val| match get_token_float(&val) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            });
-            (x, y, z, w).into()
-        },
+        Vertex::from,
     )
 }
 
@@ -359,38 +836,9 @@ pub(crate) fn parse_vertex_normal<'a>(
     map(
         preceded(
             token_match!(Token::VertexNormal),
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                token_match!(Token::Float(_) | Token::Int(_)),
-                token_match!(Token::Float(_) | Token::Int(_)),
-            ),
+            (float(), float(), float()),
         ),
-        |(x, y, z)| {
-            let (x, y, z) = (
-                match get_token_float(&x) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
-                match get_token_float(&y) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
-                match get_token_float(&z) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
-            );
-            (x, y, z).into()
-        },
+        Normal::from,
     )
 }
 
@@ -399,36 +847,20 @@ pub(crate) fn parse_vertex_texture<'a>(
     map(
         preceded(
             token_match!(Token::VertexTexture),
-            (
-                token_match!(Token::Float(_) | Token::Int(_)),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-                opt(token_match!(Token::Float(_) | Token::Int(_))),
-            ),
+            (float(), opt(float()), opt(float())),
         ),
-        |(u, v, w)| {
-            let u = match get_token_float(&u) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            let v = v.map(|val| match get_token_float(&val) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            });
-            let w = w.map(|val| match get_token_float(&val) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            });
-            (u, v, w).into()
-        },
+        Texture::from,
+    )
+}
+
+pub(crate) fn parse_vertex_param<'a>(
+) -> impl Parser<TokenSet<'a>, Output = ParamVertex, Error = error::Error<TokenSet<'a>>> {
+    map(
+        preceded(
+            token_match!(Token::VertexParam),
+            (float(), opt(float()), opt(float())),
+        ),
+        ParamVertex::from,
     )
 }
 
@@ -437,50 +869,7 @@ pub(crate) fn parse_face<'a>(
     preceded(
         token_match!(Token::Face),
         fold_many1(
-            map(
-                (
-                    token_match!(Token::Int(_)),
-                    opt(preceded(
-                        token_match!(Token::Slash),
-                        opt(token_match!(Token::Int(_))),
-                    )),
-                    opt(preceded(
-                        token_match!(Token::Slash),
-                        opt(token_match!(Token::Int(_))),
-                    )),
-                ),
-                |(v, t, n)| {
-                    let v = match get_token_int(&v) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    };
-                    let t = match t {
-                        Some(t) => t.map(|tex| match get_token_int(&tex) {
-                            Ok(s) => s,
-                            Err(e) => {
-                                log::error!("{}", e);
-                                Default::default()
-                            },
-                        }),
-                        None => None,
-                    };
-
-                    let n = match n {
-                        Some(n) => n.map(|norm| match get_token_int(&norm) {
-                            Ok(s) => s,
-                            Err(e) => {
-                                log::error!("{}", e);
-                                Default::default()
-                            },
-                        }),
-                        None => None,
-                    };
-                    (v, t, n).into()
-                },
-            ),
+            parse_face_element(),
             Face::default,
             |mut f: Face, item: FaceElement| {
                 f.elements.push(item);
@@ -490,36 +879,27 @@ pub(crate) fn parse_face<'a>(
     )
 }
 
+/// Matches a `v`, `v/vt`, `v//vn` or `v/vt/vn` index reference.
+pub(crate) fn parse_face_element<'a>(
+) -> impl Parser<TokenSet<'a>, Output = FaceElement, Error = error::Error<TokenSet<'a>>> {
+    map(
+        (
+            int(),
+            opt(preceded(token_match!(Token::Slash), opt(int()))),
+            opt(preceded(token_match!(Token::Slash), opt(int()))),
+        ),
+        |(v, t, n)| (v, t.flatten(), n.flatten()).into(),
+    )
+}
+
 pub(crate) fn parse_line<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = Line, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::Line),
         fold_many1(
             map(
-                (
-                    token_match!(Token::Int(_)),
-                    opt(preceded(
-                        token_match!(Token::Slash),
-                        opt(token_match!(Token::Int(_))),
-                    )),
-                ),
-                |(v, t)| {
-                    let v = match get_token_int(&v) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    };
-                    let t = t.flatten().map(|tex| match get_token_int(&tex) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    });
-                    (v, t).into()
-                },
+                (int(), opt(preceded(token_match!(Token::Slash), opt(int())))),
+                |(v, t)| (v, t.flatten()).into(),
             ),
             Line::default,
             |mut f: Line, item: LineElement| {
@@ -534,20 +914,10 @@ pub(crate) fn parse_point<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = Point, Error = error::Error<TokenSet<'a>>> {
     preceded(
         token_match!(Token::Point),
-        fold_many1(
-            map(token_match!(Token::Int(_)), |v| match get_token_int(&v) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            }),
-            Point::default,
-            |mut f: Point, item: i32| {
-                f.elements.push(item);
-                f
-            },
-        ),
+        fold_many1(int(), Point::default, |mut f: Point, item: i32| {
+            f.elements.push(item);
+            f
+        }),
     )
 }
 
@@ -556,261 +926,158 @@ pub(crate) fn parse_group<'a>(
     map(
         preceded(
             token_match!(Token::Group),
-            many1(map(
-                token_match!(Token::String(_)),
-                |s| match get_token_string(&s) {
-                    Ok(s) => s.into(),
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
+            many0(map_res(
+                token_match!(Token::String(_) | Token::Int(_)),
+                |s| get_token_string(&s).map(String::from),
             )),
         ),
-        ModelElement::Group,
+        |groups| {
+            if groups.is_empty() {
+                // A `g` without names goes back to the default group.
+                ModelElement::Group(vec!["default".into()])
+            } else {
+                ModelElement::Group(groups)
+            }
+        },
     )
 }
 
 pub(crate) fn parse_mat_lib<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::MaterialLib),
-            many1(map(
-                token_match!(Token::String(_)),
-                |s| match get_token_string(&s) {
-                    Ok(s) => s.into(),
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                },
-            )),
-        ),
-        ModelElement::MaterialLib,
+        preceded(token_match!(Token::MaterialLib), string()),
+        |libs| ModelElement::MaterialLib(split_file_names(&libs)),
     )
 }
 
+/// Splits the file names of an `mtllib` statement. The names are separated
+/// by whitespace, but only after a name ending in `.mtl`, so names which
+/// contain spaces are kept whole.
+fn split_file_names(line: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = line.trim();
+    while !rest.is_empty() {
+        let end = rest
+            .match_indices(char::is_whitespace)
+            .map(|(i, _)| i)
+            .find(|i| {
+                rest.get(i.saturating_sub(4)..*i)
+                    .is_some_and(|s| s.eq_ignore_ascii_case(".mtl"))
+            })
+            .unwrap_or(rest.len());
+        names.push(rest[..end].to_string());
+        rest = rest[end..].trim_start();
+    }
+    names
+}
+
 pub(crate) fn parse_material<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::UseMaterial),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-
-            ModelElement::Material(res.into())
-        },
+        preceded(token_match!(Token::UseMaterial), string()),
+        ModelElement::Material,
     )
 }
 
 pub(crate) fn parse_obj_name<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
-    map(
+    map_res(
         preceded(
             token_match!(Token::Object),
             token_match!(Token::String(_) | Token::Int(_)),
         ),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            ModelElement::ObjName(res.into())
-        },
+        |s| get_token_string(&s).map(|s| ModelElement::ObjName(s.into())),
     )
 }
 
 pub(crate) fn parse_smoothing<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
-    map(
-        preceded(
-            token_match!(Token::Smoothing),
-            alt((
-                token_match!(Token::Int(_)),
-                map(token_match!(Token::String(_)), |s| {
-                    let val = match get_on_off_from_str(&s) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            log::error!("{}", e);
-                            Default::default()
-                        },
-                    };
-                    if !val {
-                        Token::Int(0)
-                    } else {
-                        log::error!("Invalid smoothing value encountered. Setting default to 1.");
-                        Token::Int(1)
-                    }
-                }),
-            )),
-        ),
-        |s| {
-            let res = match get_token_int(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            ModelElement::Smoothing(res)
-        },
+    preceded(
+        token_match!(Token::Smoothing),
+        alt((
+            map(int(), ModelElement::Smoothing),
+            map(on_off(), |on| {
+                if on {
+                    ModelElement::Recovered(
+                        Box::new(ModelElement::Smoothing(1)),
+                        "Smoothing can't be turned `on`, using smoothing group 1".into(),
+                    )
+                } else {
+                    ModelElement::Smoothing(0)
+                }
+            }),
+        )),
+    )
+}
+
+pub(crate) fn parse_merging_group<'a>(
+) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
+    preceded(
+        token_match!(Token::MergingGroup),
+        alt((
+            map((int(), opt(float())), |(group_id, resolution)| {
+                ModelElement::MergingGroup(group_id, resolution)
+            }),
+            map_res(on_off(), |on| match on {
+                true => Err("Merging can't be turned `on` without a group number"),
+                false => Ok(ModelElement::MergingGroup(0, None)),
+            }),
+        )),
     )
 }
 
 pub(crate) fn parse_bevel<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(token_match!(Token::Bevel), token_match!(Token::String(_))),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-
-            if let Ok(flag) = res.parse::<bool>() {
-                ModelElement::Bevel(flag)
-            } else {
-                ModelElement::Bevel(false)
-            }
-        },
+        preceded(token_match!(Token::Bevel), on_off()),
+        ModelElement::Bevel,
     )
 }
 
 pub(crate) fn parse_c_interp<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(token_match!(Token::CInterp), token_match!(Token::String(_))),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-
-            if let Ok(flag) = res.parse::<bool>() {
-                ModelElement::CInterp(flag)
-            } else {
-                ModelElement::CInterp(false)
-            }
-        },
+        preceded(token_match!(Token::CInterp), on_off()),
+        ModelElement::CInterp,
     )
 }
 
 pub(crate) fn parse_d_interp<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(token_match!(Token::DInterp), token_match!(Token::String(_))),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-
-            if let Ok(flag) = res.parse::<bool>() {
-                ModelElement::DInterp(flag)
-            } else {
-                ModelElement::DInterp(false)
-            }
-        },
+        preceded(token_match!(Token::DInterp), on_off()),
+        ModelElement::DInterp,
     )
 }
 
 pub(crate) fn parse_lod<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(token_match!(Token::Lod), token_match!(Token::Int(_))),
-        |s| {
-            let res = match get_token_int(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-            ModelElement::Lod(res)
-        },
+        preceded(token_match!(Token::Lod), map_res(int(), u8::try_from)),
+        ModelElement::Lod,
     )
 }
 
 pub(crate) fn parse_shadow_obj<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::ShadowObj),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-
-            ModelElement::ShadowObj(res.into())
-        },
+        preceded(token_match!(Token::ShadowObj), string()),
+        ModelElement::ShadowObj,
     )
 }
 
 pub(crate) fn parse_trace_obj<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::TraceObj),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-
-            ModelElement::TraceObj(res.into())
-        },
+        preceded(token_match!(Token::TraceObj), string()),
+        ModelElement::TraceObj,
     )
 }
 
 pub(crate) fn parse_texture_lib<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::TextureMapLib),
-            many1(map(token_match!(Token::String(_)), |s| {
-                let res = match get_token_string(&s) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        log::error!("{}", e);
-                        Default::default()
-                    },
-                };
-
-                res.into()
-            })),
-        ),
+        preceded(token_match!(Token::TextureMapLib), many1(string())),
         ModelElement::TextureLib,
     )
 }
@@ -818,20 +1085,7 @@ pub(crate) fn parse_texture_lib<'a>(
 pub(crate) fn parse_texture_map<'a>(
 ) -> impl Parser<TokenSet<'a>, Output = ModelElement, Error = error::Error<TokenSet<'a>>> {
     map(
-        preceded(
-            token_match!(Token::UseTextureMap),
-            token_match!(Token::String(_)),
-        ),
-        |s| {
-            let res = match get_token_string(&s) {
-                Ok(s) => s,
-                Err(e) => {
-                    log::error!("{}", e);
-                    Default::default()
-                },
-            };
-
-            ModelElement::TextureMap(res.into())
-        },
+        preceded(token_match!(Token::UseTextureMap), string()),
+        ModelElement::TextureMap,
     )
 }
@@ -0,0 +1,98 @@
+use std::{collections::HashMap, result::Result};
+
+use crate::{
+    math::{cross, dot, norm, normalize, polygon_normal, sub},
+    ElementLocation, Model, Normal, ValidationError,
+};
+
+impl Model {
+    /// Replaces the normals of the model with ones computed from the faces,
+    /// and points every face element at the new normals.
+    ///
+    /// Faces in the same non-zero smoothing group share a normal at each
+    /// vertex they have in common, which is the average of their own
+    /// normals weighted by their area and by the angle of their corner at
+    /// the vertex. A vertex used by faces in several smoothing groups gets
+    /// one normal per group. Faces in smoothing group 0 are flat, with a
+    /// single normal used by all their elements.
+    ///
+    /// Lines and points don't have normals. The normal indices of free-form
+    /// surface control points are cleared, as they would refer to the
+    /// replaced normals. Nothing is changed if a face refers to a vertex
+    /// which doesn't exist.
+    pub fn generate_normals(&mut self) -> Result<(), ValidationError> {
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+        let mut smooth: HashMap<(usize, i32), usize> = HashMap::new();
+        let mut assigned = Vec::with_capacity(self.faces.len());
+        for (face, f) in self.faces.iter().enumerate() {
+            let vertices = f
+                .elements
+                .iter()
+                .enumerate()
+                .map(|(element, e)| {
+                    self.vertex_index(e.vertex_index)
+                        .map_err(|error| ValidationError::Index {
+                            location: ElementLocation::Face { face, element },
+                            error,
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let points = vertices
+                .iter()
+                .map(|v| {
+                    let v = &self.vertices[*v];
+                    [v.x, v.y, v.z]
+                })
+                .collect::<Vec<_>>();
+            // The length of the normal is twice the area of the face.
+            let area_normal = polygon_normal(&points);
+
+            if f.smoothing_group == 0 {
+                normals.push(normalize(area_normal));
+                assigned.push(vec![normals.len() - 1; points.len()]);
+                continue;
+            }
+            let mut indices = Vec::with_capacity(points.len());
+            let n = points.len();
+            for (i, vertex) in vertices.into_iter().enumerate() {
+                let angle = corner_angle(points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+                let key = (vertex, f.smoothing_group);
+                let index = *smooth.entry(key).or_insert_with(|| {
+                    normals.push([0.0; 3]);
+                    normals.len() - 1
+                });
+                let sum = &mut normals[index];
+                for (s, a) in sum.iter_mut().zip(area_normal) {
+                    *s += a * angle;
+                }
+                indices.push(index);
+            }
+            assigned.push(indices);
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|n| {
+                let [x, y, z] = normalize(n);
+                Normal::new(x, y, z)
+            })
+            .collect();
+        for (f, indices) in self.faces.iter_mut().zip(assigned) {
+            for (e, index) in f.elements.iter_mut().zip(indices) {
+                e.normal_index = Some(index as i32 + 1);
+            }
+        }
+        for s in &mut self.free_form.surfaces {
+            for v in &mut s.vertices {
+                v.normal_index = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The angle at `b` between the edges to `a` and `c`.
+fn corner_angle(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> f32 {
+    let (u, v) = (sub(a, b), sub(c, b));
+    norm(cross(u, v)).atan2(dot(u, v))
+}
@@ -0,0 +1,64 @@
+/// Controls what happens when a statement can't be parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Parsing stops at the first statement which can't be parsed, and an
+    /// error naming it is returned.
+    #[default]
+    Strict,
+    /// Statements which can't be parsed are skipped up to the end of their
+    /// line and recorded as a `Diagnostic`, and parsing carries on.
+    Lenient,
+}
+
+/// Controls what happens when a `g` statement names a group which
+/// already exists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GroupMode {
+    /// The existing group is continued, keeping its elements and taking the
+    /// material and render attributes in effect.
+    #[default]
+    Merge,
+    /// Every `g` statement starts a new instance of the group. Instances
+    /// after the first are stored under the group name followed by `#`
+    /// and the number of the instance, such as `wall#2`, while
+    /// `Group::name` keeps the name from the file.
+    Split,
+}
+
+/// Settings for `load_obj_with_options` and `load_mtl_with_options`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseOptions {
+    /// How statements which can't be parsed are handled.
+    pub mode: ParseMode,
+    /// Turns every `Diagnostic`, including warnings, into a hard error
+    /// which stops parsing.
+    pub diagnostics_as_errors: bool,
+    /// How groups which are named again are handled. Only used for
+    /// obj content.
+    pub group_mode: GroupMode,
+    /// Turns relative (negative) indices in faces, lines and points into
+    /// absolute ones, counting back from the number of vertices, texture
+    /// coordinates or normals declared before the statement. Only used for
+    /// obj content.
+    ///
+    /// This is on by default. When it is turned off, relative indices are
+    /// kept as written, and the methods which look up the data an element
+    /// refers to report them as `IndexError::Relative`.
+    ///
+    /// The resolved indices still start at 1. The `indices` methods of
+    /// `Face`, `Line` and `Point` turn them into checked zero-based
+    /// positions, which `Model::to_indexed_mesh` uses for its `u32` index
+    /// buffers.
+    pub resolve_indices: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            mode: ParseMode::default(),
+            diagnostics_as_errors: false,
+            group_mode: GroupMode::default(),
+            resolve_indices: true,
+        }
+    }
+}
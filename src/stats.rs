@@ -0,0 +1,271 @@
+use std::result::Result;
+
+use crate::{
+    math::{cross, distance, dot, norm, polygon_normal, sub},
+    triangulate::triangulate,
+    ElementLocation, Model, Triangulation, ValidationError,
+};
+
+/// A box aligned with the axes.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BoundingBox {
+    /// The smallest x, y and z coordinates.
+    pub min: [f32; 3],
+    /// The largest x, y and z coordinates.
+    pub max: [f32; 3],
+}
+
+impl BoundingBox {
+    /// The point in the middle of the box.
+    pub fn center(&self) -> [f32; 3] {
+        [0, 1, 2].map(|i| (self.min[i] + self.max[i]) / 2.0)
+    }
+
+    /// The length of the box along each axis.
+    pub fn size(&self) -> [f32; 3] {
+        sub(self.max, self.min)
+    }
+}
+
+/// A sphere containing a set of points. It is close to, but not always,
+/// the smallest such sphere.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BoundingSphere {
+    /// The center of the sphere.
+    pub center: [f32; 3],
+    /// The radius of the sphere.
+    pub radius: f32,
+}
+
+/// Measurements of the geometry of a model, group or object.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// The box around the vertices, or `None` if there are none.
+    pub bounding_box: Option<BoundingBox>,
+    /// A sphere around the vertices, or `None` if there are none.
+    pub bounding_sphere: Option<BoundingSphere>,
+    /// The average position of the vertices, or `None` if there are none.
+    pub centroid: Option<[f32; 3]>,
+    /// The total area of the faces.
+    pub surface_area: f32,
+    /// The number of faces.
+    pub polygons: usize,
+    /// The number of triangles the faces split into.
+    pub triangles: usize,
+    /// The number of faces with fewer than three elements or no area.
+    pub degenerate_faces: usize,
+    /// The number of lines.
+    pub lines: usize,
+    /// The number of points.
+    pub points: usize,
+}
+
+impl Model {
+    /// Measures the whole model. The bounds contain every entry in
+    /// `Model::vertices`, including those only used by free-form geometry.
+    ///
+    /// An error is returned for the first face, line or point which refers
+    /// to a vertex that doesn't exist.
+    pub fn statistics(&self) -> Result<Statistics, ValidationError> {
+        let mut s = Collector::new(self);
+        s.faces(0..self.faces.len())?;
+        s.lines(0..self.lines.len())?;
+        s.points(0..self.points.len())?;
+        s.used = (0..self.vertices.len()).collect();
+        Ok(s.finish())
+    }
+
+    /// Measures the faces, lines and points in the group named `name`. The
+    /// bounds only contain the vertices they use. Nothing is counted if
+    /// there is no such group.
+    ///
+    /// An error is returned for the first face, line or point which refers
+    /// to a vertex that doesn't exist.
+    pub fn group_statistics(&self, name: &str) -> Result<Statistics, ValidationError> {
+        let mut s = Collector::new(self);
+        if let Some(g) = self.groups.get(name) {
+            s.faces(g.faces.iter().copied())?;
+            s.lines(g.lines.iter().copied())?;
+            s.points(g.points.iter().copied())?;
+        }
+        Ok(s.finish())
+    }
+
+    /// Measures the faces, lines and points in the object at position
+    /// `object` in `Model::objects`. The bounds only contain the vertices
+    /// they use. Nothing is counted if there is no such object.
+    ///
+    /// An error is returned for the first face, line or point which refers
+    /// to a vertex that doesn't exist.
+    pub fn object_statistics(&self, object: usize) -> Result<Statistics, ValidationError> {
+        let mut s = Collector::new(self);
+        if let Some(o) = self.objects.get(object) {
+            s.faces(o.faces.iter().copied())?;
+            s.lines(o.lines.iter().copied())?;
+            s.points(o.points.iter().copied())?;
+        }
+        Ok(s.finish())
+    }
+}
+
+/// Gathers the statistics of a set of elements, and the vertices they use.
+struct Collector<'a> {
+    model: &'a Model,
+    stats: Statistics,
+    seen: Vec<bool>,
+    used: Vec<usize>,
+}
+
+impl<'a> Collector<'a> {
+    fn new(model: &'a Model) -> Self {
+        Self {
+            model,
+            stats: Statistics::default(),
+            seen: vec![false; model.vertices.len()],
+            used: Vec::new(),
+        }
+    }
+
+    fn vertex(
+        &mut self,
+        index: i32,
+        location: ElementLocation,
+    ) -> Result<[f32; 3], ValidationError> {
+        let i = self
+            .model
+            .vertex_index(index)
+            .map_err(|error| ValidationError::Index { location, error })?;
+        if !self.seen[i] {
+            self.seen[i] = true;
+            self.used.push(i);
+        }
+        let v = &self.model.vertices[i];
+        Ok([v.x, v.y, v.z])
+    }
+
+    fn faces(&mut self, faces: impl Iterator<Item = usize>) -> Result<(), ValidationError> {
+        let model = self.model;
+        for face in faces {
+            let f = &model.faces[face];
+            let points = f
+                .elements
+                .iter()
+                .enumerate()
+                .map(|(element, e)| {
+                    self.vertex(e.vertex_index, ElementLocation::Face { face, element })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let area = triangulate(&points, Triangulation::EarClipping)
+                .into_iter()
+                .map(|[a, b, c]| {
+                    norm(cross(sub(points[b], points[a]), sub(points[c], points[a]))) / 2.0
+                })
+                .sum::<f32>();
+            let longest = points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .map(|(a, b)| distance(*a, *b))
+                .fold(0.0, f32::max);
+            let flat = norm(polygon_normal(&points)) <= f32::EPSILON * longest * longest;
+
+            self.stats.polygons += 1;
+            self.stats.triangles += points.len().saturating_sub(2);
+            self.stats.surface_area += area;
+            if points.len() < 3 || flat {
+                self.stats.degenerate_faces += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn lines(&mut self, lines: impl Iterator<Item = usize>) -> Result<(), ValidationError> {
+        let model = self.model;
+        for line in lines {
+            for (element, e) in model.lines[line].elements.iter().enumerate() {
+                self.vertex(e.vertex_index, ElementLocation::Line { line, element })?;
+            }
+            self.stats.lines += 1;
+        }
+        Ok(())
+    }
+
+    fn points(&mut self, points: impl Iterator<Item = usize>) -> Result<(), ValidationError> {
+        let model = self.model;
+        for point in points {
+            for (element, v) in model.points[point].elements.iter().enumerate() {
+                self.vertex(*v, ElementLocation::Point { point, element })?;
+            }
+            self.stats.points += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Statistics {
+        let points = self
+            .used
+            .iter()
+            .map(|i| {
+                let v = &self.model.vertices[*i];
+                [v.x, v.y, v.z]
+            })
+            .collect::<Vec<_>>();
+        Statistics {
+            bounding_box: bounding_box(&points),
+            bounding_sphere: bounding_sphere(&points),
+            centroid: centroid(&points),
+            ..self.stats
+        }
+    }
+}
+
+pub(crate) fn bounding_box(points: &[[f32; 3]]) -> Option<BoundingBox> {
+    let first = *points.first()?;
+    Some(points.iter().fold(
+        BoundingBox {
+            min: first,
+            max: first,
+        },
+        |b, p| BoundingBox {
+            min: [0, 1, 2].map(|i| b.min[i].min(p[i])),
+            max: [0, 1, 2].map(|i| b.max[i].max(p[i])),
+        },
+    ))
+}
+
+fn centroid(points: &[[f32; 3]]) -> Option<[f32; 3]> {
+    if points.is_empty() {
+        return None;
+    }
+    let n = points.len() as f32;
+    let sum = points
+        .iter()
+        .fold([0.0; 3], |s, p| [0, 1, 2].map(|i| s[i] + p[i]));
+    Some(sum.map(|x| x / n))
+}
+
+/// Ritter's algorithm: a sphere through two points far apart, grown to
+/// take in any points left outside.
+fn bounding_sphere(points: &[[f32; 3]]) -> Option<BoundingSphere> {
+    let farthest = |from: [f32; 3]| {
+        points
+            .iter()
+            .copied()
+            .max_by(|a, b| distance(from, *a).total_cmp(&distance(from, *b)))
+    };
+    let a = farthest(*points.first()?)?;
+    let b = farthest(a)?;
+    let mut center = [0, 1, 2].map(|i| (a[i] + b[i]) / 2.0);
+    let mut radius = distance(a, b) / 2.0;
+    for p in points {
+        let d = sub(*p, center);
+        let length = dot(d, d).sqrt();
+        if length > radius {
+            let grown = (radius + length) / 2.0;
+            let shift = (grown - radius) / length;
+            center = [0, 1, 2].map(|i| center[i] + d[i] * shift);
+            radius = grown;
+        }
+    }
+    Some(BoundingSphere { center, radius })
+}
@@ -0,0 +1,134 @@
+use std::{collections::HashMap, result::Result};
+
+use thiserror::Error;
+
+use crate::{
+    math::{cross, normalize},
+    IndexedMesh,
+};
+
+/// The error produced when tangents can't be generated for a mesh.
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TangentError {
+    /// Tangents follow the texture coordinates, so the mesh needs them.
+    #[error("The mesh doesn't have texture coordinates")]
+    MissingTextures,
+
+    /// Tangents are perpendicular to the normals, so the mesh needs them.
+    #[error("The mesh doesn't have normals")]
+    MissingNormals,
+}
+
+impl IndexedMesh {
+    /// Fills `IndexedMesh::tangents` with a tangent for each vertex, using
+    /// the reference MikkTSpace implementation, which most tools that bake
+    /// or render normal maps follow.
+    ///
+    /// The tangent points along the u texture direction, perpendicular to
+    /// the normal. The fourth component is 1.0 when the bitangent is
+    /// `cross(normal, tangent)`, and -1.0 when it points the other way,
+    /// which happens when the texture is mirrored.
+    ///
+    /// MikkTSpace gives each corner of a triangle its own tangent, so a
+    /// vertex whose corners get different tangents is split, new vertices
+    /// may be appended and `IndexedMesh::indices` may change. Vertices which
+    /// aren't used by any triangle get an arbitrary direction perpendicular
+    /// to their normal.
+    pub fn generate_tangents(&mut self) -> Result<(), TangentError> {
+        if self.textures.is_empty() {
+            return Err(TangentError::MissingTextures);
+        }
+        if self.normals.is_empty() {
+            return Err(TangentError::MissingNormals);
+        }
+
+        let mut triangles = Triangles {
+            mesh: self,
+            tangents: vec![None; self.indices.len()],
+        };
+        // Nothing is generated when every triangle is degenerate, which
+        // leaves the fallback for all the vertices.
+        bevy_mikktspace::generate_tangents(&mut triangles);
+        let corners = triangles.tangents;
+
+        let mut tangents: Vec<Option<[f32; 4]>> = vec![None; self.positions.len()];
+        let mut split = HashMap::new();
+        for (index, tangent) in self.indices.iter_mut().zip(corners) {
+            let Some(tangent) = tangent else {
+                continue;
+            };
+            let v = *index as usize;
+            match tangents[v] {
+                None => tangents[v] = Some(tangent),
+                Some(t) if t == tangent => {},
+                Some(_) => {
+                    *index = *split
+                        .entry((v, tangent.map(f32::to_bits)))
+                        .or_insert_with(|| {
+                            self.positions.push(self.positions[v]);
+                            self.textures.push(self.textures[v]);
+                            self.normals.push(self.normals[v]);
+                            tangents.push(Some(tangent));
+                            (self.positions.len() - 1) as u32
+                        });
+                },
+            }
+        }
+
+        self.tangents = tangents
+            .into_iter()
+            .zip(&self.normals)
+            .map(|(tangent, n)| {
+                tangent.unwrap_or_else(|| {
+                    let axis = if n[0].abs() < 0.9 {
+                        [1.0, 0.0, 0.0]
+                    } else {
+                        [0.0, 1.0, 0.0]
+                    };
+                    let [x, y, z] = normalize(cross(*n, axis));
+                    [x, y, z, 1.0]
+                })
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+/// The triangles of a mesh, as the MikkTSpace implementation reads them,
+/// and the tangent it generates for each of their corners.
+struct Triangles<'a> {
+    mesh: &'a IndexedMesh,
+    tangents: Vec<Option<[f32; 4]>>,
+}
+
+impl Triangles<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> usize {
+        self.mesh.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for Triangles<'_> {
+    fn num_faces(&self) -> usize {
+        self.mesh.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.positions[self.vertex(face, vert)]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.normals[self.vertex(face, vert)]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.mesh.textures[self.vertex(face, vert)]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = Some(tangent);
+    }
+}
@@ -0,0 +1,839 @@
+use std::result::Result;
+
+use thiserror::Error;
+
+use crate::{
+    math::{cross, distance, dot, norm, normalize, sub},
+    model::GroupedElement,
+    Basis, Curve, Curve2D, CurveApproximation, CurveReference, CurveSurfaceType, Face, FaceElement,
+    FreeFormAttributes, Line, LineElement, Model, Normal, Surface, SurfaceApproximation, Texture,
+    Vertex,
+};
+
+/// The error produced when free-form geometry can't be tessellated.
+#[derive(Error, Debug)]
+pub enum TessellationError {
+    /// A curve couldn't be evaluated. `index` is the position of the
+    /// curve in `FreeForm::curves`.
+    #[error("Curve {index}: `{message}`")]
+    Curve { index: usize, message: String },
+
+    /// A surface couldn't be evaluated. `index` is the position of the
+    /// surface in `FreeForm::surfaces`.
+    #[error("Surface {index}: `{message}`")]
+    Surface { index: usize, message: String },
+}
+
+/// Settings for `Model::tessellate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TessellationOptions {
+    /// How curves are subdivided into line segments, unless a `ctech`
+    /// statement says otherwise.
+    pub curve: CurveApproximation,
+    /// How surfaces are subdivided into polygons, unless an `stech`
+    /// statement says otherwise.
+    pub surface: SurfaceApproximation,
+}
+
+impl Default for TessellationOptions {
+    fn default() -> Self {
+        Self {
+            curve: CurveApproximation::Parametric { resolution: 8.0 },
+            surface: SurfaceApproximation::ParametricA {
+                u_resolution: 8.0,
+                v_resolution: 8.0,
+            },
+        }
+    }
+}
+
+impl Model {
+    /// Turns the free-form curves and surfaces of the model into regular
+    /// lines and faces.
+    ///
+    /// Bezier and B-spline geometry, rational or not, is supported. The
+    /// generated vertices, normals and texture coordinates are appended to
+    /// the model, and the lines and faces are added to the groups, object
+    /// and material which were current when the curve or surface was
+    /// defined. Surfaces are trimmed by their `trim` and `hole` loops.
+    /// Special curves and points are not taken into account.
+    ///
+    /// The tessellated curves and surfaces are removed from
+    /// `Model::free_form`, along with the connections between the surfaces.
+    /// Nothing is changed if any of them can't be tessellated.
+    ///
+    /// # Arguments
+    /// * options - Settings controlling how finely geometry is subdivided
+    pub fn tessellate(&mut self, options: &TessellationOptions) -> Result<(), TessellationError> {
+        let mut out = Output::default();
+        let mut lines = Vec::new();
+        for (index, curve) in self.free_form.curves.iter().enumerate() {
+            let line = tessellate_curve(self, curve, options, &mut out)
+                .map_err(|message| TessellationError::Curve { index, message })?;
+            lines.push(line);
+        }
+        let mut faces = Vec::new();
+        for (index, surface) in self.free_form.surfaces.iter().enumerate() {
+            let f = tessellate_surface(self, surface, options, &mut out)
+                .map_err(|message| TessellationError::Surface { index, message })?;
+            faces.push(f);
+        }
+
+        self.vertices.extend(out.vertices);
+        self.normals.extend(out.normals);
+        self.textures.extend(out.textures);
+        let curves = std::mem::take(&mut self.free_form.curves);
+        for (c, line) in curves.iter().zip(lines) {
+            let material = c.material_name.as_deref();
+            self.insert(GroupedElement::Line(line), &c.groups, c.object, material);
+        }
+        let surfaces = std::mem::take(&mut self.free_form.surfaces);
+        self.free_form.connections.clear();
+        for (s, faces) in surfaces.iter().zip(faces) {
+            let material = s.material_name.as_deref();
+            for f in faces {
+                self.insert(GroupedElement::Face(f), &s.groups, s.object, material);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Vertex data generated by a tessellation, which is only added to the
+/// model once everything has been tessellated.
+#[derive(Default)]
+struct Output {
+    vertices: Vec<Vertex>,
+    normals: Vec<Normal>,
+    textures: Vec<Texture>,
+}
+
+impl Output {
+    /// Adds a vertex, returning its index in the model.
+    fn vertex(&mut self, model: &Model, p: [f32; 3]) -> i32 {
+        self.vertices.push(Vertex::new(p[0], p[1], p[2], None));
+        (model.vertices.len() + self.vertices.len()) as i32
+    }
+
+    /// Adds a normal, returning its index in the model.
+    fn normal(&mut self, model: &Model, n: [f32; 3]) -> i32 {
+        self.normals.push(Normal::new(n[0], n[1], n[2]));
+        (model.normals.len() + self.normals.len()) as i32
+    }
+
+    /// Adds a texture coordinate, returning its index in the model.
+    fn texture(&mut self, model: &Model, t: [f32; 2]) -> i32 {
+        self.textures.push(Texture::new(t[0], Some(t[1]), None));
+        (model.textures.len() + self.textures.len()) as i32
+    }
+}
+
+/// A subdivision technique for one parameter direction.
+#[derive(Clone, Copy)]
+enum Technique {
+    Parametric(f32),
+    Spatial(f32),
+    Curvature(f32, f32),
+}
+
+impl From<CurveApproximation> for Technique {
+    fn from(value: CurveApproximation) -> Self {
+        match value {
+            CurveApproximation::Parametric { resolution } => Self::Parametric(resolution),
+            CurveApproximation::Spatial { max_length } => Self::Spatial(max_length),
+            CurveApproximation::Curvature {
+                max_distance,
+                max_angle,
+            } => Self::Curvature(max_distance, max_angle),
+        }
+    }
+}
+
+/// Splits a surface approximation into the techniques for the u and v
+/// directions.
+fn surface_techniques(value: SurfaceApproximation) -> (Technique, Technique) {
+    match value {
+        SurfaceApproximation::ParametricA {
+            u_resolution,
+            v_resolution,
+        } => (
+            Technique::Parametric(u_resolution),
+            Technique::Parametric(v_resolution),
+        ),
+        SurfaceApproximation::ParametricB { resolution } => (
+            Technique::Parametric(resolution),
+            Technique::Parametric(resolution),
+        ),
+        SurfaceApproximation::Spatial { max_length } => (
+            Technique::Spatial(max_length),
+            Technique::Spatial(max_length),
+        ),
+        SurfaceApproximation::Curvature {
+            max_distance,
+            max_angle,
+        } => (
+            Technique::Curvature(max_distance, max_angle),
+            Technique::Curvature(max_distance, max_angle),
+        ),
+    }
+}
+
+/// Number of points sampled along a polynomial segment when measuring it.
+const MEASURE_SAMPLES: usize = 16;
+
+impl Technique {
+    /// The number of subdivisions for a polynomial segment, given points
+    /// sampled densely along it.
+    fn subdivisions(&self, points: &[[f32; 3]]) -> usize {
+        let count = match *self {
+            Self::Parametric(resolution) => resolution.ceil(),
+            Self::Spatial(max_length) => length(points) / max_length.max(f32::EPSILON),
+            Self::Curvature(max_distance, max_angle) => {
+                let turn = turning_angle(points);
+                let by_angle = turn.to_degrees() / max_angle.max(f32::EPSILON);
+                // Treat the segment as a circular arc, and limit the distance
+                // between each chord and the arc.
+                let radius = length(points) / turn.max(f32::EPSILON);
+                let by_distance = if max_distance < radius {
+                    turn / (2.0 * (1.0 - max_distance / radius).acos())
+                } else {
+                    1.0
+                };
+                by_angle.max(by_distance)
+            },
+        };
+        if count.is_finite() {
+            (count.ceil() as usize).clamp(1, 4096)
+        } else {
+            1
+        }
+    }
+
+    /// Whether subdividing needs the geometry of the segment to be measured.
+    fn measures(&self) -> bool {
+        !matches!(self, Self::Parametric(_))
+    }
+}
+
+fn length(points: &[[f32; 3]]) -> f32 {
+    points.windows(2).map(|w| distance(w[0], w[1])).sum()
+}
+
+/// The total angle, in radians, between successive chords of a polyline.
+fn turning_angle(points: &[[f32; 3]]) -> f32 {
+    points
+        .windows(3)
+        .map(|w| {
+            let a = sub(w[1], w[0]);
+            let b = sub(w[2], w[1]);
+            let len = norm(a) * norm(b);
+            if len > f32::EPSILON {
+                (dot(a, b) / len).clamp(-1.0, 1.0).acos()
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+/// The basis functions of one parameter direction of a curve or surface,
+/// expressed as a B-spline knot vector.
+struct Spline {
+    degree: usize,
+    knots: Vec<f32>,
+    rational: bool,
+}
+
+impl Spline {
+    /// Builds the basis from the free-form attributes and `parm` values.
+    /// `count` is the number of control points in this direction, when
+    /// it's known.
+    fn new(
+        curve_type: Option<CurveSurfaceType>,
+        degree: Option<u32>,
+        parameters: &[f32],
+        range: (f32, f32),
+        count: Option<usize>,
+    ) -> Result<Self, String> {
+        let curve_type = curve_type.ok_or("No `cstype` statement given")?;
+        let degree = degree.ok_or("No `deg` statement given")? as usize;
+        if degree == 0 {
+            return Err("The degree has to be at least 1".into());
+        }
+        let knots = match curve_type.basis {
+            Basis::BSpline => parameters.to_vec(),
+            Basis::Bezier => {
+                let breaks = if parameters.is_empty() {
+                    // Without `parm` values, spread the segments evenly over
+                    // the range of the curve.
+                    let count = count.ok_or("No `parm` statement given")?;
+                    if count < 2 || (count - 1) % degree != 0 {
+                        return Err(format!(
+                            "{} control points can't form a Bezier curve of degree {}",
+                            count, degree
+                        ));
+                    }
+                    let segments = (count - 1) / degree;
+                    (0..=segments)
+                        .map(|i| range.0 + (range.1 - range.0) * i as f32 / segments as f32)
+                        .collect()
+                } else {
+                    parameters.to_vec()
+                };
+                // A Bezier curve is a B-spline whose inner knots all have
+                // a multiplicity of the degree.
+                let mut knots = vec![breaks[0]];
+                for b in &breaks {
+                    knots.extend(std::iter::repeat_n(*b, degree));
+                }
+                knots.push(breaks[breaks.len() - 1]);
+                knots
+            },
+            basis => return Err(format!("{:?} geometry can't be tessellated", basis)),
+        };
+        if knots.len() < 2 * (degree + 1) {
+            return Err(format!(
+                "{} parameter values are too few for degree {}",
+                parameters.len(),
+                degree
+            ));
+        }
+        if knots.windows(2).any(|w| w[1] < w[0]) {
+            return Err("Parameter values have to be increasing".into());
+        }
+        let spline = Self {
+            degree,
+            knots,
+            rational: curve_type.rational,
+        };
+        match count {
+            Some(count) if count != spline.count() => Err(format!(
+                "Expected {} control points, but found {}",
+                spline.count(),
+                count
+            )),
+            _ => Ok(spline),
+        }
+    }
+
+    /// The number of control points the basis needs.
+    fn count(&self) -> usize {
+        self.knots.len() - self.degree - 1
+    }
+
+    /// The values within `range` at which the polynomial segments meet,
+    /// including the ends of the range.
+    fn breakpoints(&self, range: (f32, f32)) -> Vec<f32> {
+        let mut res = vec![range.0];
+        for k in &self.knots {
+            if *k > range.0 && *k < range.1 && res.last() != Some(k) {
+                res.push(*k);
+            }
+        }
+        res.push(range.1);
+        res
+    }
+
+    /// Finds the knot span containing `t`.
+    fn span(&self, t: f32) -> usize {
+        let n = self.count() - 1;
+        if t >= self.knots[n + 1] {
+            return n;
+        }
+        if t <= self.knots[self.degree] {
+            return self.degree;
+        }
+        let (mut low, mut high) = (self.degree, n + 1);
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if t < self.knots[mid] {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        low
+    }
+
+    /// The non-zero basis functions of `degree` at `t` in `span`.
+    fn basis(&self, span: usize, degree: usize, t: f32) -> Vec<f32> {
+        let mut n = vec![0.0; degree + 1];
+        let mut left = vec![0.0; degree + 1];
+        let mut right = vec![0.0; degree + 1];
+        n[0] = 1.0;
+        for j in 1..=degree {
+            left[j] = t - self.knots[span + 1 - j];
+            right[j] = self.knots[span + j] - t;
+            let mut saved = 0.0;
+            for r in 0..j {
+                let denom = right[r + 1] + left[j - r];
+                let temp = if denom != 0.0 { n[r] / denom } else { 0.0 };
+                n[r] = saved + right[r + 1] * temp;
+                saved = left[j - r] * temp;
+            }
+            n[j] = saved;
+        }
+        n
+    }
+
+    /// Evaluates the basis at `t`. Returns the index of the first control
+    /// point involved, and the values and derivatives of the basis
+    /// functions for it and the following `degree` control points.
+    fn eval(&self, t: f32) -> (usize, Vec<f32>, Vec<f32>) {
+        let p = self.degree;
+        let span = self.span(t);
+        let n = self.basis(span, p, t);
+        let lower = self.basis(span, p - 1, t);
+        let d = (0..=p)
+            .map(|j| {
+                let i = span - p + j;
+                let mut res = 0.0;
+                if j > 0 {
+                    let denom = self.knots[i + p] - self.knots[i];
+                    if denom != 0.0 {
+                        res += lower[j - 1] / denom;
+                    }
+                }
+                if j < p {
+                    let denom = self.knots[i + p + 1] - self.knots[i + 1];
+                    if denom != 0.0 {
+                        res -= lower[j] / denom;
+                    }
+                }
+                res * p as f32
+            })
+            .collect();
+        (span - p, n, d)
+    }
+
+    /// The parameter values to sample at. `measure` samples the geometry
+    /// between two parameter values, for techniques which need it.
+    fn samples(
+        &self,
+        range: (f32, f32),
+        technique: Technique,
+        measure: impl Fn(f32, f32) -> Vec<Vec<[f32; 3]>>,
+    ) -> Vec<f32> {
+        let breaks = self.breakpoints(range);
+        let mut res = vec![breaks[0]];
+        for w in breaks.windows(2) {
+            let count = if technique.measures() {
+                measure(w[0], w[1])
+                    .iter()
+                    .map(|points| technique.subdivisions(points))
+                    .max()
+                    .unwrap_or(1)
+            } else {
+                technique.subdivisions(&[])
+            };
+            for i in 1..=count {
+                res.push(w[0] + (w[1] - w[0]) * i as f32 / count as f32);
+            }
+        }
+        res
+    }
+}
+
+/// Evenly spaced parameter values between `t0` and `t1`, used to measure
+/// geometry.
+fn measure_points(t0: f32, t1: f32) -> impl Iterator<Item = f32> {
+    (0..=MEASURE_SAMPLES).map(move |i| t0 + (t1 - t0) * i as f32 / MEASURE_SAMPLES as f32)
+}
+
+/// A control point in homogeneous form.
+#[derive(Clone, Copy)]
+struct ControlPoint<const N: usize> {
+    position: [f32; N],
+    weight: f32,
+}
+
+/// Combines control points with basis function values. Returns the point and
+/// the derivative of each basis function set in `derivatives`.
+fn combine<const N: usize>(
+    points: impl Iterator<Item = (ControlPoint<N>, f32, Vec<f32>)>,
+    derivatives: usize,
+) -> ([f32; N], Vec<[f32; N]>) {
+    let mut p = [0.0; N];
+    let mut w = 0.0;
+    let mut dp = vec![[0.0; N]; derivatives];
+    let mut dw = vec![0.0; derivatives];
+    for (cp, n, d) in points {
+        for (p, x) in p.iter_mut().zip(cp.position) {
+            *p += x * cp.weight * n;
+        }
+        w += cp.weight * n;
+        for ((dp, dw), d) in dp.iter_mut().zip(&mut dw).zip(d) {
+            for (dp, x) in dp.iter_mut().zip(cp.position) {
+                *dp += x * cp.weight * d;
+            }
+            *dw += cp.weight * d;
+        }
+    }
+    if w == 0.0 {
+        w = 1.0;
+    }
+    let point = p.map(|x| x / w);
+    let derivs = dp
+        .iter()
+        .zip(&dw)
+        .map(|(d, dw)| {
+            let mut res = [0.0; N];
+            for ((res, d), p) in res.iter_mut().zip(d).zip(point) {
+                *res = (d - p * dw) / w;
+            }
+            res
+        })
+        .collect();
+    (point, derivs)
+}
+
+/// A curve, evaluated from its control points.
+struct CurveEval<const N: usize> {
+    spline: Spline,
+    points: Vec<ControlPoint<N>>,
+}
+
+impl<const N: usize> CurveEval<N> {
+    fn eval(&self, t: f32) -> [f32; N] {
+        let (first, n, _) = self.spline.eval(t);
+        combine(
+            n.iter()
+                .enumerate()
+                .map(|(j, n)| (self.points[first + j], *n, vec![])),
+            0,
+        )
+        .0
+    }
+}
+
+fn vertex_point(model: &Model, index: i32, rational: bool) -> Result<ControlPoint<3>, String> {
+    let v = &model.vertices[model.vertex_index(index).map_err(|e| e.to_string())?];
+    Ok(ControlPoint {
+        position: [v.x, v.y, v.z],
+        weight: if rational { v.w.unwrap_or(1.0) } else { 1.0 },
+    })
+}
+
+fn tessellate_curve(
+    model: &Model,
+    curve: &Curve,
+    options: &TessellationOptions,
+    out: &mut Output,
+) -> Result<Line, String> {
+    let range = (curve.start, curve.end);
+    let spline = spline_u(
+        &curve.attributes,
+        &curve.body.parameters_u,
+        range,
+        curve.vertices.len(),
+    )?;
+    let points = curve
+        .vertices
+        .iter()
+        .map(|v| vertex_point(model, *v, spline.rational))
+        .collect::<Result<_, _>>()?;
+    let eval = CurveEval { spline, points };
+    let technique = curve.technique.unwrap_or(options.curve).into();
+    let samples = eval.spline.samples(range, technique, |t0, t1| {
+        vec![measure_points(t0, t1).map(|t| eval.eval(t)).collect()]
+    });
+    let elements = samples
+        .iter()
+        .map(|t| LineElement::new(out.vertex(model, eval.eval(*t)), None))
+        .collect();
+    Ok(Line::new(elements))
+}
+
+fn spline_u(
+    attributes: &FreeFormAttributes,
+    parameters: &[f32],
+    range: (f32, f32),
+    count: usize,
+) -> Result<Spline, String> {
+    Spline::new(
+        attributes.curve_type,
+        attributes.degree_u,
+        parameters,
+        range,
+        Some(count),
+    )
+}
+
+/// Evaluates the `curv2` segments of a trimming loop into a polygon in the
+/// parameter space of a surface.
+fn trim_polygon(
+    model: &Model,
+    loop_: &[CurveReference],
+    options: &TessellationOptions,
+) -> Result<Vec<[f32; 2]>, String> {
+    let mut res = Vec::new();
+    for r in loop_ {
+        let curve: &Curve2D = usize::try_from(r.curve_index - 1)
+            .ok()
+            .and_then(|i| model.free_form.curves_2d.get(i))
+            .ok_or_else(|| format!("Curve index {} is out of range", r.curve_index))?;
+        let spline = spline_u(
+            &curve.attributes,
+            &curve.body.parameters_u,
+            (r.start, r.end),
+            curve.param_vertices.len(),
+        )?;
+        let points = curve
+            .param_vertices
+            .iter()
+            .map(|vp| {
+                let p = usize::try_from(vp - 1)
+                    .ok()
+                    .and_then(|i| model.param_vertices.get(i))
+                    .ok_or_else(|| format!("Parameter vertex index {} is out of range", vp))?;
+                Ok(ControlPoint {
+                    position: [p.u, p.v.unwrap_or_default()],
+                    weight: if spline.rational {
+                        p.w.unwrap_or(1.0)
+                    } else {
+                        1.0
+                    },
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        let eval = CurveEval { spline, points };
+        let technique = curve.technique.unwrap_or(options.curve).into();
+        let samples = eval.spline.samples((r.start, r.end), technique, |t0, t1| {
+            vec![measure_points(t0, t1)
+                .map(|t| {
+                    let [u, v] = eval.eval(t);
+                    [u, v, 0.0]
+                })
+                .collect()]
+        });
+        res.extend(samples.iter().map(|t| eval.eval(*t)));
+    }
+    Ok(res)
+}
+
+/// Even-odd test of whether `p` is inside `polygon`.
+fn contains(polygon: &[[f32; 2]], p: [f32; 2]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a[1] > p[1]) != (b[1] > p[1])
+            && p[0] < (b[0] - a[0]) * (p[1] - a[1]) / (b[1] - a[1]) + a[0]
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// A surface, evaluated from its control points.
+struct SurfaceEval {
+    u: Spline,
+    v: Spline,
+    points: Vec<ControlPoint<3>>,
+    textures: Option<Vec<ControlPoint<2>>>,
+}
+
+impl SurfaceEval {
+    /// Evaluates the position and partial derivatives at (u, v).
+    fn eval(&self, u: f32, v: f32) -> ([f32; 3], [f32; 3], [f32; 3]) {
+        let (p, d) = combine(
+            self.weights(u, v).map(|(i, n, d)| (self.points[i], n, d)),
+            2,
+        );
+        (p, d[0], d[1])
+    }
+
+    fn texture(&self, u: f32, v: f32) -> Option<[f32; 2]> {
+        let textures = self.textures.as_ref()?;
+        Some(combine(self.weights(u, v).map(|(i, n, d)| (textures[i], n, d)), 0).0)
+    }
+
+    /// The control point index, basis value and partial derivatives of
+    /// every control point involved at (u, v).
+    fn weights(&self, u: f32, v: f32) -> impl Iterator<Item = (usize, f32, Vec<f32>)> {
+        let (first_u, nu, du) = self.u.eval(u);
+        let (first_v, nv, dv) = self.v.eval(v);
+        let count_u = self.u.count();
+        let mut res = Vec::with_capacity(nu.len() * nv.len());
+        for (j, (nv, dv)) in nv.iter().zip(&dv).enumerate() {
+            for (i, (nu, du)) in nu.iter().zip(&du).enumerate() {
+                let index = (first_v + j) * count_u + first_u + i;
+                res.push((index, nu * nv, vec![du * nv, nu * dv]));
+            }
+        }
+        res.into_iter()
+    }
+}
+
+fn tessellate_surface(
+    model: &Model,
+    surface: &Surface,
+    options: &TessellationOptions,
+    out: &mut Output,
+) -> Result<Vec<Face>, String> {
+    let attributes = &surface.attributes;
+    let body = &surface.body;
+    let range_u = (surface.start_u, surface.end_u);
+    let range_v = (surface.start_v, surface.end_v);
+    let count = surface.vertices.len();
+    // A single Bezier patch doesn't need `parm` statements.
+    let patch = body.parameters_u.is_empty()
+        && body.parameters_v.is_empty()
+        && attributes
+            .degree_u
+            .zip(attributes.degree_v)
+            .map(|(u, v)| (u as usize + 1) * (v as usize + 1) == count)
+            .unwrap_or_default();
+    let hint = |degree: Option<u32>| patch.then(|| degree.unwrap_or_default() as usize + 1);
+    let u = Spline::new(
+        attributes.curve_type,
+        attributes.degree_u,
+        &body.parameters_u,
+        range_u,
+        hint(attributes.degree_u),
+    )?;
+    let v = Spline::new(
+        attributes.curve_type,
+        attributes.degree_v.or(attributes.degree_u),
+        &body.parameters_v,
+        range_v,
+        hint(attributes.degree_v),
+    )?;
+    if u.count() * v.count() != count {
+        return Err(format!(
+            "Expected {} control points, but found {}",
+            u.count() * v.count(),
+            count
+        ));
+    }
+
+    let points = surface
+        .vertices
+        .iter()
+        .map(|e| vertex_point(model, e.vertex_index, u.rational))
+        .collect::<Result<Vec<_>, _>>()?;
+    let textures = surface
+        .vertices
+        .iter()
+        .zip(&points)
+        .map(|(e, p)| {
+            let t = model
+                .textures
+                .get(usize::try_from(e.texture_index? - 1).ok()?)?;
+            Some(ControlPoint {
+                position: [t.u, t.v.unwrap_or_default()],
+                weight: p.weight,
+            })
+        })
+        .collect();
+    let eval = SurfaceEval {
+        u,
+        v,
+        points,
+        textures,
+    };
+
+    let (technique_u, technique_v) =
+        surface_techniques(surface.technique.unwrap_or(options.surface));
+    let breaks_u = eval.u.breakpoints(range_u);
+    let breaks_v = eval.v.breakpoints(range_v);
+    let iso_values = |breaks: &[f32]| {
+        let mut res = breaks.to_vec();
+        res.extend(breaks.windows(2).map(|w| (w[0] + w[1]) / 2.0));
+        res
+    };
+    let samples_u = eval.u.samples(range_u, technique_u, |t0, t1| {
+        iso_values(&breaks_v)
+            .into_iter()
+            .map(|v| measure_points(t0, t1).map(|u| eval.eval(u, v).0).collect())
+            .collect()
+    });
+    let samples_v = eval.v.samples(range_v, technique_v, |t0, t1| {
+        iso_values(&breaks_u)
+            .into_iter()
+            .map(|u| measure_points(t0, t1).map(|v| eval.eval(u, v).0).collect())
+            .collect()
+    });
+
+    let trims = body
+        .trims
+        .iter()
+        .map(|l| trim_polygon(model, l, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let holes = body
+        .holes
+        .iter()
+        .map(|l| trim_polygon(model, l, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let keep = |p: [f32; 2]| {
+        (trims.is_empty() || trims.iter().any(|t| contains(t, p)))
+            && !holes.iter().any(|h| contains(h, p))
+    };
+
+    let center = ((range_u.0 + range_u.1) / 2.0, (range_v.0 + range_v.1) / 2.0);
+    let mut grid: Vec<Option<FaceElement>> = vec![None; samples_u.len() * samples_v.len()];
+    let mut element = |out: &mut Output, i: usize, j: usize| {
+        let index = j * samples_u.len() + i;
+        if let Some(e) = grid[index] {
+            return e;
+        }
+        let (u, v) = (samples_u[i], samples_v[j]);
+        let (p, du, dv) = eval.eval(u, v);
+        let mut n = cross(du, dv);
+        if norm(n) <= f32::EPSILON {
+            // The surface is degenerate here, such as at a pole, so take the
+            // normal from just inside it instead.
+            let (_, du, dv) = eval.eval(u + (center.0 - u) * 1e-3, v + (center.1 - v) * 1e-3);
+            n = cross(du, dv);
+        }
+        let t = eval.texture(u, v).unwrap_or([
+            (u - range_u.0) / (range_u.1 - range_u.0),
+            (v - range_v.0) / (range_v.1 - range_v.0),
+        ]);
+        let e = FaceElement::new(
+            out.vertex(model, p),
+            Some(out.texture(model, t)),
+            Some(out.normal(model, normalize(n))),
+        );
+        grid[index] = Some(e);
+        e
+    };
+
+    let mut faces = Vec::new();
+    for j in 0..samples_v.len() - 1 {
+        for i in 0..samples_u.len() - 1 {
+            let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+            let uv = corners.map(|(i, j)| [samples_u[i], samples_v[j]]);
+            let first = keep(centroid(&[uv[0], uv[1], uv[2]]));
+            let second = keep(centroid(&[uv[0], uv[2], uv[3]]));
+            let triangles: &[&[usize]] = match (first, second) {
+                (true, true) => &[&[0, 1, 2, 3]],
+                (true, false) => &[&[0, 1, 2]],
+                (false, true) => &[&[0, 2, 3]],
+                (false, false) => &[],
+            };
+            for corner_indices in triangles {
+                let elements = corner_indices
+                    .iter()
+                    .map(|c| element(out, corners[*c].0, corners[*c].1))
+                    .collect();
+                faces.push(Face::new(
+                    elements,
+                    surface.smoothing_group,
+                    surface.merging_group,
+                ));
+            }
+        }
+    }
+    Ok(faces)
+}
+
+fn centroid(points: &[[f32; 2]]) -> [f32; 2] {
+    let n = points.len() as f32;
+    let sum = points
+        .iter()
+        .fold([0.0, 0.0], |acc, p| [acc[0] + p[0], acc[1] + p[1]]);
+    [sum[0] / n, sum[1] / n]
+}
@@ -12,7 +12,7 @@ fn parse_double_comment_test() {
     vn -1 0.000157759 5.71832e-005
     f 11250//11250 4406//4406 31248//31248
     f 9238//9238 25314//25314 21852//21852";
-    let model = load_obj(&content).unwrap();
+    let model = load_obj(content).unwrap();
     assert_eq!(model.vertices.len(), 1);
     assert_eq!(model.vertices[0].x, 0.1102022);
     assert_eq!(model.vertices[0].y, 0.74011);
@@ -21,7 +21,7 @@ fn parse_double_comment_test() {
     assert_eq!(model.normals[0].x, -1.0);
     assert_eq!(model.normals[0].y, 0.000157759);
     assert_eq!(model.normals[0].z, 0.0000571832);
-    let faces = &model.faces["default"];
+    let faces = model.group_faces("default").collect::<Vec<_>>();
     assert_eq!(faces.len(), 2);
     let face = &faces[0];
     assert_eq!(
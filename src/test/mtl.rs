@@ -4,6 +4,7 @@ use crate::{
         ReflectionMap,
     },
     tokenizer::{parse_mtl, Token},
+    Diagnostic, MaterialError, ObjError, ParseMode, ParseOptions, Severity, Span,
 };
 
 #[test]
@@ -24,6 +25,53 @@ fn parse_simple_comment_2() {
     assert_eq!(tokens.len(), 0);
 }
 
+#[test]
+fn parse_token_spans() {
+    let vert = "newmtl stone\n  Kd 0.5 0.5 0.5";
+    let tokens = parse_mtl(vert).unwrap();
+    assert_eq!(tokens.len(), 6);
+    assert_eq!(
+        tokens.span(0),
+        Span {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    );
+    assert_eq!(
+        tokens.span(2),
+        Span {
+            offset: 15,
+            line: 2,
+            column: 3,
+        }
+    );
+    assert_eq!(
+        tokens.span(5),
+        Span {
+            offset: 26,
+            line: 2,
+            column: 14,
+        }
+    );
+}
+
+#[test]
+fn material_before_newmtl_span() {
+    let input = "# comment\n   Kd 0.5 0.5 0.5\nnewmtl stone";
+    let err = crate::load_mtl(input).unwrap_err();
+    let span = Span {
+        offset: 13,
+        line: 2,
+        column: 4,
+    };
+    assert!(matches!(
+        err,
+        ObjError::MaterialParse(MaterialError::NewMaterial { span: s }) if s == span
+    ));
+    assert_eq!(err.span(), Some(span));
+}
+
 #[test]
 fn parse_texres() {
     let vert = "-texres 512";
@@ -264,8 +312,9 @@ fn parse_tf_spectral() {
 }
 
 macro_rules! parse_material_test {
-    ($name:ident, $val:expr, $exp:expr) => {
+    ($(#[$attr:meta])* $name:ident, $val:expr, $exp:expr) => {
         #[test]
+        $(#[$attr])*
         fn $name() {
             let res = crate::load_mtl(&$val).unwrap();
             assert_eq!(res.len(), 1);
@@ -475,6 +524,7 @@ parse_material_test!(
 );
 
 parse_material_test!(
+    #[allow(clippy::excessive_precision)]
     moon_test,
     "# Material Count: 1
     
@@ -573,3 +623,94 @@ illum 0",
         ..Default::default()
     }
 );
+
+#[test]
+fn strict_unrecognized_material_statement() {
+    let input = "newmtl red\nKd 1 0 0\nfoo bar\nillum 2\n";
+    let res = crate::load_mtl(input);
+    match res {
+        Err(ObjError::MaterialParse(MaterialError::Parse { message, span })) => {
+            assert_eq!(message, "Unrecognized statement: `foo`");
+            assert_eq!(
+                span,
+                Span {
+                    offset: 20,
+                    line: 3,
+                    column: 1
+                }
+            );
+        },
+        _ => panic!("Expected a parse error, got {:?}", res),
+    }
+}
+
+#[test]
+fn lenient_skips_bad_material_statements() {
+    let input = "Kd 0 0 1\nnewmtl red\nKd 1 0 x\nillum 2\n";
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    let (materials, diagnostics) = crate::load_mtl_with_options(input, &options).unwrap();
+    assert_eq!(
+        materials,
+        vec![Material {
+            name: "red".into(),
+            illumination_mode: Some(2),
+            ..Default::default()
+        }]
+    );
+    assert_eq!(
+        diagnostics.to_vec(),
+        vec![
+            Diagnostic::new(
+                Severity::Error,
+                "Statement found before any `newmtl` statement".into(),
+                Span {
+                    offset: 0,
+                    line: 1,
+                    column: 1
+                },
+                Some("Kd")
+            ),
+            Diagnostic::new(
+                Severity::Error,
+                "Invalid `Kd` statement".into(),
+                Span {
+                    offset: 20,
+                    line: 3,
+                    column: 1
+                },
+                Some("Kd")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn invalid_material_values() {
+    let input = "newmtl red\nillum -1\nmap_Kd -clamp maybe red.png\nNs 10\n";
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    let (materials, diagnostics) = crate::load_mtl_with_options(input, &options).unwrap();
+    assert_eq!(
+        materials,
+        vec![Material {
+            name: "red".into(),
+            specular_exponent: Some(10.0),
+            ..Default::default()
+        }]
+    );
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.severity, d.span.line, d.statement))
+            .collect::<Vec<_>>(),
+        vec![
+            (Severity::Error, 2, Some("illum")),
+            (Severity::Error, 3, Some("map_Kd"))
+        ]
+    );
+}
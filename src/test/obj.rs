@@ -2,9 +2,14 @@ use nom::Parser;
 
 use crate::{
     model,
-    model::{Face, FaceElement, ModelElement, Vertex},
+    model::{Face, FaceElement, ModelElement, ParamVertex, Vertex},
     tokenizer::{parse_obj, Token},
-    Line, LineElement, Point, Texture,
+    Basis, BoundingBox, Connection, Curve, Curve2D, CurveApproximation, CurveReference,
+    CurveSurfaceType, Diagnostic, ElementIndices, ElementLocation, FreeFormAttributes,
+    FreeFormBody, Group, GroupMode, IndexError, IndexedMesh, Line, LineElement, MaterialRange,
+    ModelError, ObjError, Object, ParseMode, ParseOptions, Point, Severity, Span, Statistics,
+    SurfaceApproximation, SurfaceEdge, TangentError, TessellationError, TessellationOptions,
+    Texture, Transform, TransformError, Triangulation, ValidationError,
 };
 
 #[test]
@@ -49,6 +54,38 @@ fn parse_vertex() {
     assert_eq!(tokens[4], Token::Float(1.0));
 }
 
+#[test]
+fn parse_token_spans() {
+    let vert = "v 1 2 3\r\n\n\tf 1/2 3";
+    let tokens = parse_obj(vert).unwrap();
+    assert_eq!(tokens.len(), 9);
+    assert_eq!(tokens[4], Token::Face);
+    assert_eq!(
+        tokens.span(3),
+        Span {
+            offset: 6,
+            line: 1,
+            column: 7,
+        }
+    );
+    assert_eq!(
+        tokens.span(4),
+        Span {
+            offset: 11,
+            line: 3,
+            column: 2,
+        }
+    );
+    assert_eq!(
+        tokens.span(6),
+        Span {
+            offset: 14,
+            line: 3,
+            column: 5,
+        }
+    );
+}
+
 #[test]
 fn parse_vertex_multiline_test() {
     let vert = "v 0.123 0.234 0.345 1.0\nv 1.123 1.234 1.345 1.0";
@@ -94,7 +131,7 @@ fn parse_vertex_texture() {
     assert_eq!(tokens[1], Token::Float(0.500));
     assert_eq!(tokens[2], Token::Int(1));
 
-    let res = model::parse_vertex_texture().parse_complete(tokens);
+    let res = model::parse_vertex_texture().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, texture) = res.ok().unwrap();
@@ -115,7 +152,7 @@ fn parse_vertex_texture2() {
     assert!(res.is_ok());
     let tokens = res.unwrap();
 
-    let res = model::parse_vertex_texture().parse_complete(tokens);
+    let res = model::parse_vertex_texture().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, texture) = res.ok().unwrap();
@@ -128,6 +165,50 @@ fn parse_vertex_texture2() {
     assert_eq!(texture, expected);
 }
 
+#[test]
+fn parse_vertex_param() {
+    let vert = "vp 0.25";
+    let res = parse_obj(vert);
+    assert!(res.is_ok());
+    let tokens = res.unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0], Token::VertexParam);
+    assert_eq!(tokens[1], Token::Float(0.25));
+
+    let res = model::parse_vertex_param().parse_complete((&tokens).into());
+
+    assert!(res.is_ok());
+    let (extra, param) = res.ok().unwrap();
+    assert_eq!(extra.len(), 0);
+    assert_eq!(param, ParamVertex::new(0.25, None, None));
+}
+
+#[test]
+fn parse_vertex_param2() {
+    let vert = "vp 0.25 1";
+    let tokens = parse_obj(vert).unwrap();
+
+    let res = model::parse_vertex_param().parse_complete((&tokens).into());
+
+    assert!(res.is_ok());
+    let (extra, param) = res.ok().unwrap();
+    assert_eq!(extra.len(), 0);
+    assert_eq!(param, ParamVertex::new(0.25, Some(1.0), None));
+}
+
+#[test]
+fn parse_vertex_param3() {
+    let vert = "vp 0.25 1 0.5";
+    let tokens = parse_obj(vert).unwrap();
+
+    let res = model::parse_vertex_param().parse_complete((&tokens).into());
+
+    assert!(res.is_ok());
+    let (extra, param) = res.ok().unwrap();
+    assert_eq!(extra.len(), 0);
+    assert_eq!(param, ParamVertex::new(0.25, Some(1.0), Some(0.5)));
+}
+
 #[test]
 fn parse_face() {
     let vert = "f 1 2 3";
@@ -141,7 +222,7 @@ fn parse_face() {
     assert_eq!(tokens[2], Token::Int(2));
     assert_eq!(tokens[3], Token::Int(3));
 
-    let res = model::parse_face().parse_complete(tokens);
+    let res = model::parse_face().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, face) = res.ok().unwrap();
@@ -166,6 +247,7 @@ fn parse_face() {
             },
         ],
         smoothing_group: 0,
+        merging_group: 0,
     };
     assert_eq!(face, expected);
 }
@@ -189,7 +271,7 @@ fn parse_face_1() {
     assert_eq!(tokens[8], Token::Slash);
     assert_eq!(tokens[9], Token::Int(4));
 
-    let res = model::parse_face().parse_complete(tokens);
+    let res = model::parse_face().parse_complete((&tokens).into());
 
     let (extra, face) = res.ok().unwrap();
     assert_eq!(extra.len(), 0);
@@ -213,6 +295,7 @@ fn parse_face_1() {
             },
         ],
         smoothing_group: 0,
+        merging_group: 0,
     };
     assert_eq!(face, expected);
 }
@@ -242,7 +325,7 @@ fn parse_face_2() {
     assert_eq!(tokens[14], Token::Slash);
     assert_eq!(tokens[15], Token::Int(5));
 
-    let res = model::parse_face().parse_complete(tokens);
+    let res = model::parse_face().parse_complete((&tokens).into());
 
     let (extra, face) = res.ok().unwrap();
     assert_eq!(extra.len(), 0);
@@ -266,6 +349,7 @@ fn parse_face_2() {
             },
         ],
         smoothing_group: 0,
+        merging_group: 0,
     };
     assert_eq!(face, expected);
 }
@@ -292,7 +376,7 @@ fn parse_face_3() {
     assert_eq!(tokens[11], Token::Slash);
     assert_eq!(tokens[12], Token::Int(4));
 
-    let res = model::parse_face().parse_complete(tokens);
+    let res = model::parse_face().parse_complete((&tokens).into());
 
     let (extra, face) = res.ok().unwrap();
     assert_eq!(extra.len(), 0);
@@ -316,6 +400,7 @@ fn parse_face_3() {
             },
         ],
         smoothing_group: 0,
+        merging_group: 0,
     };
     assert_eq!(face, expected);
 }
@@ -330,7 +415,7 @@ fn parse_face_4() {
     assert!(res.is_ok());
     let tokens = res.unwrap();
 
-    let res = model::parse_face().parse_complete(tokens);
+    let res = model::parse_face().parse_complete((&tokens).into());
 
     let (extra, face) = res.ok().unwrap();
     assert_eq!(extra.len(), 0);
@@ -359,6 +444,7 @@ fn parse_face_4() {
             },
         ],
         smoothing_group: 0,
+        merging_group: 0,
     };
     assert_eq!(face, expected);
 }
@@ -373,7 +459,7 @@ fn parse_face_trailing_slash() {
     assert!(res.is_ok());
     let tokens = res.unwrap();
 
-    let res = model::parse_face().parse_complete(tokens);
+    let res = model::parse_face().parse_complete((&tokens).into());
 
     let (extra, face) = res.ok().unwrap();
     assert_eq!(extra.len(), 0);
@@ -397,6 +483,7 @@ fn parse_face_trailing_slash() {
             },
         ],
         smoothing_group: 0,
+        merging_group: 0,
     };
     assert_eq!(face, expected);
 }
@@ -411,7 +498,7 @@ fn parse_face_trailing_slash_slash() {
     assert!(res.is_ok());
     let tokens = res.unwrap();
 
-    let res = model::parse_face().parse_complete(tokens);
+    let res = model::parse_face().parse_complete((&tokens).into());
 
     let (extra, face) = res.ok().unwrap();
     assert_eq!(extra.len(), 0);
@@ -435,6 +522,7 @@ fn parse_face_trailing_slash_slash() {
             },
         ],
         smoothing_group: 0,
+        merging_group: 0,
     };
     assert_eq!(face, expected);
 }
@@ -452,7 +540,7 @@ fn parse_point() {
     assert_eq!(tokens[2], Token::Int(2));
     assert_eq!(tokens[3], Token::Int(3));
 
-    let res = model::parse_point().parse_complete(tokens);
+    let res = model::parse_point().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, point) = res.ok().unwrap();
@@ -477,7 +565,7 @@ fn parse_line() {
     assert_eq!(tokens[2], Token::Int(2));
     assert_eq!(tokens[3], Token::Int(3));
 
-    let res = model::parse_line().parse_complete(tokens);
+    let res = model::parse_line().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, line) = res.ok().unwrap();
@@ -509,7 +597,7 @@ fn parse_line_texture_struct() {
 
     assert!(res.is_ok());
     let tokens = res.unwrap();
-    let res = model::parse_line().parse_complete(tokens);
+    let res = model::parse_line().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, line) = res.ok().unwrap();
@@ -543,7 +631,7 @@ fn parse_line_trailing_slash_struct() {
 
     assert!(res.is_ok());
     let tokens = res.unwrap();
-    let res = model::parse_line().parse_complete(tokens);
+    let res = model::parse_line().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, line) = res.ok().unwrap();
@@ -578,7 +666,7 @@ fn simple_material() {
     assert_eq!(tokens[0], Token::MaterialLib);
     assert_eq!(tokens[1], Token::String("some_mtl_file.mtl".into()));
 
-    let res = model::parse_mat_lib().parse_complete(tokens);
+    let res = model::parse_mat_lib().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, model) = res.ok().unwrap();
@@ -600,7 +688,7 @@ fn simple_group() {
     assert_eq!(tokens[0], Token::Group);
     assert_eq!(tokens[1], Token::String("some_group".into()));
 
-    let res = model::parse_group().parse_complete(tokens);
+    let res = model::parse_group().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, model) = res.ok().unwrap();
@@ -620,7 +708,7 @@ fn simple_object() {
     assert_eq!(tokens[0], Token::Object);
     assert_eq!(tokens[1], Token::String("some_object".into()));
 
-    let res = model::parse_obj_name().parse_complete(tokens);
+    let res = model::parse_obj_name().parse_complete((&tokens).into());
 
     assert!(res.is_ok());
     let (extra, model) = res.ok().unwrap();
@@ -663,7 +751,7 @@ fn cube_test() {
     # End of file
     ";
 
-    let res = crate::load_obj(&input).unwrap();
+    let res = crate::load_obj(input).unwrap();
 
     assert_eq!(res.vertices.len(), 8);
     assert_eq!(
@@ -742,8 +830,8 @@ fn cube_test() {
     let group = &res.groups["default"];
     assert_eq!(group.material_name, "Default".to_string());
     assert_eq!(res.normals.len(), 0);
-    assert_eq!(res.faces.len(), 1);
-    let face_group = &res.faces["default"];
+    assert_eq!(res.group_faces("default").count(), res.faces.len());
+    let face_group = &res.faces;
     assert_eq!(face_group.len(), 6);
     assert_eq!(
         face_group[0],
@@ -925,7 +1013,7 @@ fn cube_test_interspersed() {
     # End of file
     ";
 
-    let res = crate::load_obj(&input).unwrap();
+    let res = crate::load_obj(input).unwrap();
 
     assert_eq!(res.vertices.len(), 8);
     assert_eq!(
@@ -1004,8 +1092,8 @@ fn cube_test_interspersed() {
     let group = &res.groups["default"];
     assert_eq!(group.material_name, "Default".to_string());
     assert_eq!(res.normals.len(), 0);
-    assert_eq!(res.faces.len(), 1);
-    let face_group = &res.faces["default"];
+    assert_eq!(res.group_faces("default").count(), res.faces.len());
+    let face_group = &res.faces;
     assert_eq!(face_group.len(), 6);
     assert_eq!(
         face_group[0],
@@ -1154,7 +1242,6 @@ fn cube_test_interspersed() {
 }
 
 #[test]
-#[ignore]
 fn cube_test_minus() {
     let input = "#	                Vertices: 8
     #	                  Points: 0
@@ -1195,7 +1282,7 @@ fn cube_test_minus() {
     # End of file
     ";
 
-    let res = crate::load_obj(&input).unwrap();
+    let res = crate::load_obj(input).unwrap();
 
     assert_eq!(res.vertices.len(), 8);
     assert_eq!(
@@ -1274,8 +1361,8 @@ fn cube_test_minus() {
     let group = &res.groups["default"];
     assert_eq!(group.material_name, "Default".to_string());
     assert_eq!(res.normals.len(), 0);
-    assert_eq!(res.faces.len(), 1);
-    let face_group = &res.faces["default"];
+    assert_eq!(res.group_faces("default").count(), res.faces.len());
+    let face_group = &res.faces;
     assert_eq!(face_group.len(), 6);
     assert_eq!(
         face_group[0],
@@ -1462,7 +1549,7 @@ fn comment_following_material_lib_test() {
     # End of file
     ";
 
-    let res = crate::load_obj(&input).unwrap();
+    let res = crate::load_obj(input).unwrap();
     assert_eq!(res.material_libs.len(), 1);
     assert!(res.material_libs.contains(&("cubelib.mtl".into())));
     assert_eq!(res.vertices.len(), 8);
@@ -1542,8 +1629,8 @@ fn comment_following_material_lib_test() {
     let group = &res.groups["default"];
     assert_eq!(group.material_name, "Default".to_string());
     assert_eq!(res.normals.len(), 0);
-    assert_eq!(res.faces.len(), 1);
-    let face_group = &res.faces["default"];
+    assert_eq!(res.group_faces("default").count(), res.faces.len());
+    let face_group = &res.faces;
     assert_eq!(face_group.len(), 6);
     assert_eq!(
         face_group[0],
@@ -1690,3 +1777,1674 @@ fn comment_following_material_lib_test() {
         }
     );
 }
+
+#[test]
+fn blank_line_run_test() {
+    let input = format!("v 1 2 3{}v 4 5 6\n", "\n    \r\n".repeat(10_000));
+    let res = crate::load_obj(&input).unwrap();
+    assert_eq!(res.vertices.len(), 2);
+    assert_eq!(res.vertices[1], Vertex::new(4.0, 5.0, 6.0, None));
+}
+
+#[test]
+fn strict_unrecognized_statement_test() {
+    let input = "v 1 2 3\nfoo bar\nv 4 5 6\n";
+    let res = crate::load_obj(input);
+    match res {
+        Err(ObjError::ModelParse(ModelError::Parse { message, span })) => {
+            assert_eq!(message, "Unrecognized statement: `foo`");
+            assert_eq!(
+                span,
+                Span {
+                    offset: 8,
+                    line: 2,
+                    column: 1
+                }
+            );
+        },
+        _ => panic!("Expected a parse error, got {:?}", res),
+    }
+}
+
+#[test]
+fn strict_malformed_face_test() {
+    let input = "v 1 2 3\nv 4 5 6\nv 7 8 9\nf 1 2 x\n";
+    let res = crate::load_obj(input);
+    match res {
+        Err(ObjError::ModelParse(ModelError::Parse { message, span })) => {
+            assert_eq!(message, "Invalid `f` statement");
+            assert_eq!(
+                span,
+                Span {
+                    offset: 30,
+                    line: 4,
+                    column: 7
+                }
+            );
+        },
+        _ => panic!("Expected a parse error, got {:?}", res),
+    }
+}
+
+#[test]
+fn lenient_skips_bad_statements_test() {
+    let input = "v 1 2 3\nvn 0.5\nv 4 5 6\nf 1 2 x\nv 7 8 9\nf 1 2 3\n";
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    let (model, diagnostics) = crate::load_obj_with_options(input, &options).unwrap();
+    assert_eq!(model.vertices.len(), 3);
+    assert_eq!(model.vertices[2], Vertex::new(7.0, 8.0, 9.0, None));
+    assert_eq!(model.group_faces("default").count(), 1);
+    assert_eq!(
+        diagnostics.to_vec(),
+        vec![
+            Diagnostic::new(
+                Severity::Error,
+                "Invalid `vn` statement".into(),
+                Span {
+                    offset: 8,
+                    line: 2,
+                    column: 1
+                },
+                Some("vn")
+            ),
+            Diagnostic::new(
+                Severity::Error,
+                "Invalid `f` statement".into(),
+                Span {
+                    offset: 29,
+                    line: 4,
+                    column: 7
+                },
+                Some("f")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn smoothing_on_warning_test() {
+    let input = "v 1 2 3\ns on\n";
+    let (model, diagnostics) =
+        crate::load_obj_with_options(input, &ParseOptions::default()).unwrap();
+    assert_eq!(model.vertices.len(), 1);
+    assert_eq!(diagnostics.errors().count(), 0);
+    assert_eq!(
+        diagnostics.warnings().collect::<Vec<_>>(),
+        vec![&Diagnostic::new(
+            Severity::Warning,
+            "Smoothing can't be turned `on`, using smoothing group 1".into(),
+            Span {
+                offset: 8,
+                line: 2,
+                column: 1
+            },
+            Some("s")
+        )]
+    );
+}
+
+#[test]
+fn diagnostics_as_errors_test() {
+    let input = "v 1 2 3\ns on\n";
+    let options = ParseOptions {
+        diagnostics_as_errors: true,
+        ..Default::default()
+    };
+    let res = crate::load_obj_with_options(input, &options);
+    match res {
+        Err(ObjError::ModelParse(ModelError::Parse { span, .. })) => assert_eq!(span.line, 2),
+        _ => panic!("Expected a parse error, got {:?}", res),
+    }
+}
+
+#[test]
+fn invalid_on_off_value_test() {
+    let input = "bevel maybe\n";
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    let (_, diagnostics) = crate::load_obj_with_options(input, &options).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].statement, Some("bevel"));
+    assert_eq!(diagnostics[0].span.line, 1);
+}
+
+#[test]
+fn large_integer_coordinate_test() {
+    let input = "v 1 2 30000000000\nf 1 2 30000000000\n";
+    let res = crate::load_obj(input);
+    match res {
+        Err(ObjError::ModelParse(ModelError::Parse { message, span })) => {
+            assert_eq!(message, "Invalid `f` statement");
+            assert_eq!(span.line, 2);
+        },
+        _ => panic!("Expected a parse error, got {:?}", res),
+    }
+    let (model, _) = crate::load_obj_with_options(
+        input,
+        &ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(model.vertices[0], Vertex::new(1.0, 2.0, 3e10, None));
+}
+
+#[test]
+fn param_vertices_test() {
+    let input = "v 1 2 3\nvp 0.1\nvp 0.2 0.3\nvp 0.4 0.5 1\nv 4 5 6\n";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.vertices.len(), 2);
+    assert_eq!(
+        res.param_vertices,
+        vec![
+            ParamVertex::new(0.1, None, None),
+            ParamVertex::new(0.2, Some(0.3), None),
+            ParamVertex::new(0.4, Some(0.5), Some(1.0)),
+        ]
+    );
+}
+
+#[test]
+fn parse_curve_surface_type_tokens() {
+    let tokens = parse_obj("cstype rat bspline").unwrap();
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[0], Token::CurveSurfaceType);
+    assert_eq!(tokens[1], Token::String("rat".into()));
+    assert_eq!(tokens[2], Token::String("bspline".into()));
+}
+
+#[test]
+fn multiple_group_names_test() {
+    let input = "v 1 2 3\nv 4 5 6\nv 7 8 9\ng front cube\nf 1 2 3\n";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.faces.len(), 1);
+    assert_eq!(res.groups["front"].faces, [0]);
+    assert_eq!(res.groups["cube"].faces, [0]);
+}
+
+#[test]
+fn numbered_and_unnamed_group_test() {
+    let input = "v 1 2 3\nv 4 5 6\nv 7 8 9\ng 1 lid\nf 1 2 3\ng\nf 3 2 1\n";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.groups["1"].faces, [0]);
+    assert_eq!(res.groups["lid"].faces, [0]);
+    assert_eq!(res.groups["default"].faces, [1]);
+}
+
+#[test]
+fn group_views_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+g a b
+f 1 2 3
+l 1 2
+g b
+f 3 2 1
+p 1
+g a b
+p 2
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.faces.len(), 2);
+    assert_eq!(res.points.len(), 2);
+
+    let first = |faces: Vec<&Face>| {
+        faces
+            .iter()
+            .map(|f| f.elements[0].vertex_index)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(first(res.group_faces("a").collect()), [1]);
+    assert_eq!(first(res.group_faces("b").collect()), [1, 3]);
+    assert_eq!(res.group_lines("a").count(), 1);
+    assert_eq!(res.group_lines("b").count(), 1);
+    let points = |name| {
+        res.group_points(name)
+            .map(|p| p.elements.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(points("a"), [vec![2]]);
+    assert_eq!(points("b"), [vec![1], vec![2]]);
+    assert_eq!(res.group_faces("missing").count(), 0);
+}
+
+#[test]
+fn keyword_names_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+o Trim
+g step Hole
+usemtl End
+  f 1 2 3
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.objects[0].name, "Trim");
+    assert_eq!(res.group_faces("step").count(), 1);
+    assert_eq!(res.group_faces("Hole").count(), 1);
+    assert_eq!(res.groups["step"].material_name, "End");
+}
+
+#[test]
+fn line_continuation_test() {
+    let joined = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3 4
+cstype bezier
+deg 3
+curv 0 1 1 2 3 4
+parm u 0 1
+end
+surf 0 1 0 1 1 2 3 4
+parm u 0 0 1 1
+parm v 0 0 1 1
+end";
+    let continued = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 \\
+  3 4
+cstype bezier
+deg 3
+curv 0 1 \\
+1 2 \\
+3 4
+parm \\
+  u 0 1
+end
+surf 0 1 0 1 \\
+  1 2 3 4
+parm u 0 0 \\
+  1 1
+parm v 0 0 1 1
+end";
+    let expected = crate::load_obj(joined).unwrap();
+    let res = crate::load_obj(continued).unwrap();
+    assert_eq!(res.faces, expected.faces);
+    assert_eq!(res.free_form, expected.free_form);
+    assert_eq!(res.free_form.curves.len(), 1);
+    assert_eq!(res.free_form.surfaces.len(), 1);
+}
+
+#[test]
+fn multi_word_names_test() {
+    let input = "
+mtllib my file.mtl other.MTL last one
+shadow_obj my shadow.obj
+v 0 0 0
+v 1 0 0
+v 0 1 0
+o My Object
+usemtl My Material
+f 1 2 3
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.material_libs, ["my file.mtl", "other.MTL", "last one"]);
+    assert_eq!(res.shadow_obj, Some("my shadow.obj".into()));
+    assert_eq!(res.objects[0].name, "My Object");
+    assert_eq!(res.groups["default"].material_name, "My Material");
+    assert_eq!(
+        res.groups["default"].material_ranges[0].material_name,
+        "My Material"
+    );
+}
+
+#[test]
+fn free_form_surface_test() {
+    let input = "
+v -1 -1 0
+v 0 -1 0
+v 1 -1 0
+v -1 0 0
+v 0 0 1
+v 1 0 0
+v -1 1 0
+v 0 1 0
+v 1 1 0
+vp 0 0
+vp 1 0
+vp 1 1
+vp 0 1
+cstype bspline
+deg 2 2
+surf 0 1 0 1 1 2 3 4 5/1 6 7 8//1 9
+parm u 0 0 0 1 1 1
+parm v 0 0 0 1 1 1
+trim 0 1 1 0 2 1
+hole 0 1 1
+scrv 0 0.5 1
+sp 1 2
+end
+cstype rat bezier
+deg 3
+curv2 1 2 3 4
+parm u 0 1
+end
+curv 0 1 1 2 3
+end
+";
+    let res = crate::load_obj(input).unwrap();
+    let free_form = &res.free_form;
+    assert_eq!(free_form.surfaces.len(), 1);
+    let surface = &free_form.surfaces[0];
+    assert_eq!(
+        surface.attributes,
+        FreeFormAttributes {
+            curve_type: Some(CurveSurfaceType::new(false, Basis::BSpline)),
+            degree_u: Some(2),
+            degree_v: Some(2),
+            ..Default::default()
+        }
+    );
+    assert_eq!((surface.start_u, surface.end_u), (0.0, 1.0));
+    assert_eq!((surface.start_v, surface.end_v), (0.0, 1.0));
+    assert_eq!(surface.vertices.len(), 9);
+    assert_eq!(surface.vertices[4], FaceElement::new(5, Some(1), None));
+    assert_eq!(surface.vertices[7], FaceElement::new(8, None, Some(1)));
+    assert_eq!(
+        surface.body,
+        FreeFormBody {
+            parameters_u: vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            parameters_v: vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            trims: vec![vec![
+                CurveReference::new(0.0, 1.0, 1),
+                CurveReference::new(0.0, 2.0, 1)
+            ]],
+            holes: vec![vec![CurveReference::new(0.0, 1.0, 1)]],
+            special_curves: vec![vec![CurveReference::new(0.0, 0.5, 1)]],
+            special_points: vec![1, 2],
+        }
+    );
+
+    assert_eq!(
+        free_form.curves_2d,
+        vec![Curve2D {
+            attributes: FreeFormAttributes {
+                curve_type: Some(CurveSurfaceType::new(true, Basis::Bezier)),
+                degree_u: Some(3),
+                ..Default::default()
+            },
+            param_vertices: vec![1, 2, 3, 4],
+            body: FreeFormBody {
+                parameters_u: vec![0.0, 1.0],
+                ..Default::default()
+            },
+            technique: None,
+        }]
+    );
+    assert_eq!(
+        free_form.curves,
+        vec![Curve {
+            attributes: free_form.curves_2d[0].attributes.clone(),
+            start: 0.0,
+            end: 1.0,
+            vertices: vec![1, 2, 3],
+            body: Default::default(),
+            groups: vec!["default".into()],
+            object: None,
+            material_name: None,
+            technique: None,
+        }]
+    );
+}
+
+#[test]
+fn free_form_body_outside_curve_test() {
+    let input = "vp 0 0\nparm u 0 1\nend\n";
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    let (_, diagnostics) = crate::load_obj_with_options(input, &options).unwrap();
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.severity, d.span.line, d.statement))
+            .collect::<Vec<_>>(),
+        vec![
+            (Severity::Error, 2, Some("parm")),
+            (Severity::Error, 3, Some("end"))
+        ]
+    );
+    assert!(crate::load_obj(input).is_err());
+}
+
+#[test]
+fn tessellate_bezier_curve_test() {
+    let input = "
+v 0 0 0
+v 1 2 0
+v 2 0 0
+g curves
+cstype bezier
+deg 2
+curv 0 1 1 2 3
+end
+";
+    let mut model = crate::load_obj(input).unwrap();
+    let options = TessellationOptions {
+        curve: CurveApproximation::Parametric { resolution: 4.0 },
+        ..Default::default()
+    };
+    model.tessellate(&options).unwrap();
+    assert!(model.free_form.curves.is_empty());
+    let lines = model.group_lines("curves").collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1);
+    let points = lines[0]
+        .elements
+        .iter()
+        .map(|e| model.vertices[e.vertex_index as usize - 1])
+        .map(|v| (v.x, v.y, v.z))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        points,
+        vec![
+            (0.0, 0.0, 0.0),
+            (0.5, 0.75, 0.0),
+            (1.0, 1.0, 0.0),
+            (1.5, 0.75, 0.0),
+            (2.0, 0.0, 0.0)
+        ]
+    );
+}
+
+#[test]
+fn tessellate_bspline_surface_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+s 2
+cstype bspline
+deg 1 1
+surf 0 1 0 1 1 2 3 4
+parm u 0 0 1 1
+parm v 0 0 1 1
+end
+";
+    let mut model = crate::load_obj(input).unwrap();
+    let options = TessellationOptions {
+        surface: SurfaceApproximation::ParametricB { resolution: 2.0 },
+        ..Default::default()
+    };
+    model.tessellate(&options).unwrap();
+    assert!(model.free_form.surfaces.is_empty());
+    let faces = model.group_faces("default").collect::<Vec<_>>();
+    assert_eq!(faces.len(), 4);
+    assert!(faces
+        .iter()
+        .all(|f| f.elements.len() == 4 && f.smoothing_group == 2));
+    assert_eq!(model.vertices.len(), 4 + 9);
+    let center = model
+        .vertices
+        .iter()
+        .position(|v| *v == Vertex::new(0.5, 0.5, 0.0, None))
+        .unwrap();
+    assert_eq!(
+        model.textures[center - 4],
+        Texture::new(0.5, Some(0.5), None)
+    );
+    assert!(model
+        .normals
+        .iter()
+        .all(|n| (n.x, n.y, n.z) == (0.0, 0.0, 1.0)));
+}
+
+#[test]
+fn tessellate_surface_hole_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vp 0.3 0.3
+vp 0.7 0.3
+vp 0.7 0.7
+vp 0.3 0.7
+vp 0.3 0.3
+cstype bspline
+deg 1
+curv2 1 2 3 4 5
+parm u 0 0 1 2 3 4 4
+end
+deg 1 1
+surf 0 1 0 1 1 2 3 4
+parm u 0 0 1 1
+parm v 0 0 1 1
+hole 0 4 1
+end
+";
+    let mut model = crate::load_obj(input).unwrap();
+    let options = TessellationOptions {
+        surface: SurfaceApproximation::ParametricB { resolution: 4.0 },
+        ..Default::default()
+    };
+    model.tessellate(&options).unwrap();
+    // The four cells in the middle are cut out by the hole.
+    assert_eq!(model.group_faces("default").count(), 12);
+    assert_eq!(model.free_form.curves_2d.len(), 1);
+}
+
+#[test]
+fn tessellate_unsupported_basis_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 2 0 0
+v 3 0 0
+cstype cardinal
+deg 3
+curv 0 1 1 2 3 4
+end
+";
+    let mut model = crate::load_obj(input).unwrap();
+    let res = model.tessellate(&Default::default());
+    assert!(matches!(
+        res,
+        Err(TessellationError::Curve { index: 0, .. })
+    ));
+    assert_eq!(model.free_form.curves.len(), 1);
+    assert_eq!(model.vertices.len(), 4);
+}
+
+#[test]
+fn objects_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 3
+o first
+f 1 2 3
+g a b
+l 1 2
+o second
+p 1
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.group_faces("default").count(), 2);
+    assert_eq!(
+        res.objects,
+        vec![
+            Object {
+                name: "first".into(),
+                groups: vec!["default".into(), "a".into(), "b".into()],
+                faces: vec![1],
+                lines: vec![0],
+                points: vec![],
+            },
+            Object {
+                name: "second".into(),
+                groups: vec!["a".into(), "b".into()],
+                faces: vec![],
+                lines: vec![],
+                points: vec![0],
+            }
+        ]
+    );
+}
+
+#[test]
+fn group_render_attributes_test() {
+    let input = "
+bevel on
+lod 50
+g a
+c_interp on
+d_interp on
+g b
+bevel off
+shadow_obj shadow.obj
+trace_obj trace.obj
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.groups["default"],
+        Group {
+            name: "default".into(),
+            bevel: true,
+            lod: 50,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        res.groups["a"],
+        Group {
+            name: "a".into(),
+            bevel: true,
+            c_interp: true,
+            d_interp: true,
+            lod: 50,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        res.groups["b"],
+        Group {
+            name: "b".into(),
+            bevel: false,
+            c_interp: true,
+            d_interp: true,
+            lod: 50,
+            ..Default::default()
+        }
+    );
+    assert_eq!(res.shadow_obj, Some("shadow.obj".into()));
+    assert_eq!(res.trace_obj, Some("trace.obj".into()));
+}
+
+#[test]
+fn group_reentry_render_attributes_test() {
+    let input = "
+v 0 0 0
+g a
+g b
+bevel on
+lod 50
+ctech cparm 4
+g a
+p 1
+g b
+bevel off
+g a b
+";
+    let res = crate::load_obj(input).unwrap();
+    assert!(!res.groups["a"].bevel);
+    assert_eq!(res.groups["a"].lod, 50);
+    assert_eq!(
+        res.groups["a"].curve_technique,
+        Some(CurveApproximation::Parametric { resolution: 4.0 })
+    );
+    assert_eq!(res.groups["a"].points, [0]);
+    assert!(!res.groups["b"].bevel);
+
+    let res = crate::load_obj("v 0 0 0\ng a\ng b\nbevel on\nlod 50\ng a\np 1\n").unwrap();
+    assert!(res.groups["a"].bevel);
+    assert_eq!(res.groups["a"].lod, 50);
+}
+
+#[test]
+fn invalid_lod_test() {
+    for input in ["lod 101\n", "lod -1\n"] {
+        match crate::load_obj(input) {
+            Err(ObjError::ModelParse(ModelError::Parse { span, .. })) => {
+                assert_eq!(span.line, 1)
+            },
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn material_ranges_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 3
+usemtl red
+f 1 2 3
+f 1 2 3
+l 1 2
+usemtl blue
+f 1 2 3
+g a
+p 1
+usemtl blue
+f 1 2 3
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.groups["default"].material_name, "blue");
+    assert_eq!(
+        res.groups["default"].material_ranges,
+        vec![
+            MaterialRange::new("red".into(), 1..3, 0..1, 0..0),
+            MaterialRange::new("blue".into(), 3..4, 1..1, 0..0)
+        ]
+    );
+    assert_eq!(
+        res.groups["a"].material_ranges,
+        vec![MaterialRange::new("blue".into(), 0..1, 0..0, 0..1)]
+    );
+
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+g a
+usemtl red
+f 1 2 3
+g b
+f 1 2 3
+usemtl blue
+g a
+f 1 2 3
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.groups["a"].material_name, "blue");
+    assert_eq!(
+        res.groups["a"].material_ranges,
+        vec![
+            MaterialRange::new("red".into(), 0..1, 0..0, 0..0),
+            MaterialRange::new("blue".into(), 1..2, 0..0, 0..0)
+        ]
+    );
+    assert_eq!(res.groups["b"].material_name, "blue");
+    assert_eq!(
+        res.groups["b"].material_ranges,
+        vec![MaterialRange::new("red".into(), 0..1, 0..0, 0..0)]
+    );
+
+    let res = crate::load_obj("g a\nusemtl red\ng b\n").unwrap();
+    assert_eq!(res.groups["b"].material_name, "red");
+}
+
+#[test]
+fn group_reentry_test() {
+    let input = "
+v 0 0 0
+g a
+usemtl red
+bevel on
+p 1
+g b
+p 1
+g a
+p 1
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.groups.len(), 3);
+    assert_eq!(res.groups["a"].material_name, "red");
+    assert!(res.groups["a"].bevel);
+    assert_eq!(res.groups["a"].points, [0, 2]);
+
+    let options = ParseOptions {
+        group_mode: GroupMode::Split,
+        ..Default::default()
+    };
+    let (res, _) = crate::load_obj_with_options(input, &options).unwrap();
+    assert_eq!(res.groups.len(), 4);
+    assert_eq!(res.groups["a"].material_name, "red");
+    assert_eq!(
+        res.groups["a#2"],
+        Group {
+            name: "a".into(),
+            material_name: "red".into(),
+            bevel: true,
+            material_ranges: vec![MaterialRange::new("red".into(), 0..0, 0..0, 0..1)],
+            points: vec![2],
+            ..Default::default()
+        }
+    );
+    assert_eq!(res.groups["a"].points, [0]);
+    assert_eq!(res.groups["a#2"].points, [2]);
+}
+
+#[test]
+fn group_order_test() {
+    let input = "
+v 0 0 0
+g zebra
+g apple
+p 1
+g mango zebra
+p 1
+l 1 1
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.groups.keys().collect::<Vec<_>>(),
+        ["default", "zebra", "apple", "mango"]
+    );
+    assert_eq!(res.groups["zebra"].points, [1]);
+    assert_eq!(res.groups["mango"].lines, [0]);
+    assert_eq!(res.group_points("apple").count(), 1);
+}
+
+#[test]
+fn merging_group_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+mg 1 0.5
+f 1 2 3
+mg off
+f 1 2 3
+mg 1
+cstype bezier
+deg 1 1
+surf 0 1 0 1 1 2 3 1
+end
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(res.merging_groups, [(1, 0.5)].into());
+    assert_eq!(res.faces[0].merging_group, 1);
+    assert_eq!(res.faces[1].merging_group, 0);
+    assert_eq!(res.free_form.surfaces[0].merging_group, 1);
+}
+
+#[test]
+fn merging_group_resolution_test() {
+    for input in ["mg 2\n", "mg 2 0\n", "mg on\n"] {
+        match crate::load_obj(input) {
+            Err(ObjError::ModelParse(ModelError::Parse { span, .. })) => {
+                assert_eq!(span.line, 1)
+            },
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn merging_group_name_test() {
+    let res = crate::load_obj("g mg\nusemtl mg\nmg 1 2\n").unwrap();
+    assert_eq!(res.groups["mg"].material_name, "mg");
+    assert_eq!(res.merging_groups, [(1, 2.0)].into());
+}
+
+#[test]
+fn approximation_technique_test() {
+    let input = "
+ctech cparm 2
+stech cparma 3 4
+g a
+stech curv 0.1 10
+ctech cspace 0.5
+g b
+ctech curv 0.2 15
+stech cparmb 5
+stech cspace 0.25
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.groups["default"].curve_technique,
+        Some(CurveApproximation::Parametric { resolution: 2.0 })
+    );
+    assert_eq!(
+        res.groups["default"].surface_technique,
+        Some(SurfaceApproximation::ParametricA {
+            u_resolution: 3.0,
+            v_resolution: 4.0
+        })
+    );
+    assert_eq!(
+        res.groups["a"].curve_technique,
+        Some(CurveApproximation::Spatial { max_length: 0.5 })
+    );
+    assert_eq!(
+        res.groups["a"].surface_technique,
+        Some(SurfaceApproximation::Curvature {
+            max_distance: 0.1,
+            max_angle: 10.0
+        })
+    );
+    assert_eq!(
+        res.groups["b"].curve_technique,
+        Some(CurveApproximation::Curvature {
+            max_distance: 0.2,
+            max_angle: 15.0
+        })
+    );
+    assert_eq!(
+        res.groups["b"].surface_technique,
+        Some(SurfaceApproximation::Spatial { max_length: 0.25 })
+    );
+    assert!(crate::load_obj("ctech cparma 1 2\n").is_err());
+}
+
+#[test]
+fn approximation_technique_name_test() {
+    let res = crate::load_obj("g ctech stech curv\nCTECH CURV 0.5 10\n").unwrap();
+    assert_eq!(res.groups.len(), 4);
+    assert_eq!(
+        res.groups["curv"].curve_technique,
+        Some(CurveApproximation::Curvature {
+            max_distance: 0.5,
+            max_angle: 10.0
+        })
+    );
+}
+
+#[test]
+fn tessellate_curve_technique_test() {
+    let input = "
+v 0 0 0
+v 1 2 0
+v 2 0 0
+ctech cparm 2
+cstype bezier
+deg 2
+curv 0 1 1 2 3
+end
+";
+    let mut model = crate::load_obj(input).unwrap();
+    assert_eq!(
+        model.free_form.curves[0].technique,
+        Some(CurveApproximation::Parametric { resolution: 2.0 })
+    );
+    model.tessellate(&Default::default()).unwrap();
+    assert_eq!(model.lines[0].elements.len(), 3);
+    assert_eq!(model.vertices[4], Vertex::new(1.0, 1.0, 0.0, None));
+}
+
+#[test]
+fn connection_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vp 0 0
+vp 1 0
+cstype bezier
+deg 1
+curv2 1 2
+end
+deg 1 1
+surf 0 1 0 1 1 2 3 4
+end
+surf 0 1 0 1 1 2 3 4
+end
+con 1 0 1 1 -1 0 1 -1
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.free_form.connections,
+        vec![Connection::new(
+            SurfaceEdge::new(1, 0.0, 1.0, 1),
+            SurfaceEdge::new(2, 0.0, 1.0, 1)
+        )]
+    );
+
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    let input = input.replace(
+        "con 1 0 1 1 -1 0 1 -1",
+        "con 1 0 1 1 3 0 1 1\ncon 1 0 1 2 2 0 1 1",
+    );
+    let (res, diagnostics) = crate::load_obj_with_options(&input, &options).unwrap();
+    assert!(res.free_form.connections.is_empty());
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), d.statement))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Surface 3 doesn't exist", Some("con")),
+            ("Curve 2 doesn't exist", Some("con"))
+        ]
+    );
+}
+
+#[test]
+fn connection_name_test() {
+    let res = crate::load_obj("o con\ng con\n").unwrap();
+    assert_eq!(res.objects[0].name, "con");
+    assert!(res.groups.contains_key("con"));
+}
+
+#[test]
+fn resolve_indices_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+vt 0 0
+vn 0 0 1
+v 0 1 0
+f -3/-1/-1 -2/-1/-1 -1/-1/-1
+l -3 -1
+p -1
+";
+    let options = ParseOptions {
+        resolve_indices: false,
+        ..Default::default()
+    };
+    let (res, _) = crate::load_obj_with_options(input, &options).unwrap();
+    assert_eq!(res.faces[0].elements[0].vertex_index, -3);
+
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.faces[0].elements,
+        vec![
+            FaceElement::new(1, Some(1), Some(1)),
+            FaceElement::new(2, Some(1), Some(1)),
+            FaceElement::new(3, Some(1), Some(1))
+        ]
+    );
+    assert_eq!(
+        res.lines[0].elements,
+        vec![LineElement::new(1, None), LineElement::new(3, None)]
+    );
+    assert_eq!(res.points[0].elements, vec![3]);
+
+    match crate::load_obj("v 0 0 0\np -2\n") {
+        Err(ObjError::ModelParse(ModelError::Parse { span, .. })) => assert_eq!(span.line, 2),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn zero_based_indices_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vn 0 0 1
+f 1/1/1 2/1/1 3/1/1
+f 1 2 4
+f 1 2 0
+l 1/1 3
+p -1
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.faces[0].indices(&res),
+        Ok(vec![
+            ElementIndices {
+                vertex: 0,
+                texture: Some(0),
+                normal: Some(0)
+            },
+            ElementIndices {
+                vertex: 1,
+                texture: Some(0),
+                normal: Some(0)
+            },
+            ElementIndices {
+                vertex: 2,
+                texture: Some(0),
+                normal: Some(0)
+            },
+        ])
+    );
+    assert_eq!(
+        res.faces[1].indices(&res),
+        Err(IndexError::OutOfRange {
+            kind: "vertices",
+            index: 4,
+            count: 3
+        })
+    );
+    assert_eq!(
+        res.faces[2].indices(&res),
+        Err(IndexError::Zero { kind: "vertices" })
+    );
+    assert_eq!(
+        res.lines[0].indices(&res).unwrap()[1],
+        ElementIndices {
+            vertex: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(res.points[0].indices(&res), Ok(vec![2]));
+    let options = ParseOptions {
+        resolve_indices: false,
+        ..Default::default()
+    };
+    let (raw, _) = crate::load_obj_with_options(input, &options).unwrap();
+    assert_eq!(
+        raw.points[0].indices(&raw),
+        Err(IndexError::Relative {
+            kind: "vertices",
+            index: -1
+        })
+    );
+    assert_eq!(res.texture_index(1), Ok(0));
+    assert!(res.normal_index(2).is_err());
+}
+
+#[test]
+fn relative_indices_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+f -3//-1 -2//-1 -1//-1
+";
+    let mut res = crate::load_obj(input).unwrap();
+    assert_eq!(res.validate(), Ok(()));
+    assert_eq!(
+        res.triangulate(Triangulation::EarClipping),
+        Ok(vec![vec![[0, 1, 2]]])
+    );
+    assert_eq!(res.to_indexed_mesh().unwrap()[0].indices, vec![0, 1, 2]);
+    assert_eq!(res.statistics().unwrap().surface_area, 0.5);
+    assert!(res.generate_normals().is_ok());
+
+    let options = ParseOptions {
+        resolve_indices: false,
+        ..Default::default()
+    };
+    let (raw, _) = crate::load_obj_with_options(input, &options).unwrap();
+    assert_eq!(raw.validate().map_err(|e| e.len()), Err(3));
+}
+
+#[test]
+fn validate_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vn 0 0 1
+f 1/1 2/1 3/1
+f 1/1 2//1 3/2
+l 0 1
+p 1 5
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.validate(),
+        Err(vec![
+            ValidationError::MixedFormat {
+                location: ElementLocation::Face {
+                    face: 1,
+                    element: 1
+                }
+            },
+            ValidationError::Index {
+                location: ElementLocation::Face {
+                    face: 1,
+                    element: 2
+                },
+                error: IndexError::OutOfRange {
+                    kind: "texture coordinates",
+                    index: 2,
+                    count: 1
+                }
+            },
+            ValidationError::Index {
+                location: ElementLocation::Line {
+                    line: 0,
+                    element: 0
+                },
+                error: IndexError::Zero { kind: "vertices" }
+            },
+            ValidationError::Index {
+                location: ElementLocation::Point {
+                    point: 0,
+                    element: 1
+                },
+                error: IndexError::OutOfRange {
+                    kind: "vertices",
+                    index: 5,
+                    count: 3
+                }
+            },
+        ])
+    );
+
+    let valid = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nl 1 2\np 3\n";
+    assert_eq!(crate::load_obj(valid).unwrap().validate(), Ok(()));
+}
+
+#[test]
+fn indexed_mesh_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 1
+g quad
+usemtl red
+f 1/1 2/1 3/1 4/1
+g quad tri
+usemtl blue
+f 1/1 2/2 3/1
+usemtl red
+f 1/1 3/1 4/1
+";
+    let res = crate::load_obj(input).unwrap();
+    let meshes = res.to_indexed_mesh().unwrap();
+    assert_eq!(meshes.len(), 4);
+    assert_eq!(
+        meshes[0],
+        IndexedMesh {
+            group: "quad".into(),
+            material_name: Some("red".into()),
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0]
+            ],
+            textures: vec![[0.0, 0.0]; 4],
+            normals: vec![],
+            tangents: vec![],
+            indices: vec![3, 0, 1, 1, 2, 3, 0, 2, 3],
+        }
+    );
+    assert_eq!(meshes[1].group, "quad");
+    assert_eq!(meshes[1].material_name.as_deref(), Some("blue"));
+    assert_eq!(meshes[1].positions.len(), 3);
+    assert_eq!(meshes[1].textures[1], [1.0, 1.0]);
+    assert_eq!(meshes[2].group, "tri");
+    assert_eq!(meshes[2].material_name.as_deref(), Some("blue"));
+    assert_eq!(meshes[3].group, "tri");
+    assert_eq!(meshes[3].indices, vec![0, 1, 2]);
+
+    let invalid = crate::load_obj("v 0 0 0\nf 1 2 1\n").unwrap();
+    assert_eq!(
+        invalid.to_indexed_mesh(),
+        Err(ValidationError::Index {
+            location: ElementLocation::Face {
+                face: 0,
+                element: 1
+            },
+            error: IndexError::OutOfRange {
+                kind: "vertices",
+                index: 2,
+                count: 1
+            }
+        })
+    );
+}
+
+#[test]
+fn triangulate_test() {
+    // A concave quad, where fanning out from the first element would cover
+    // the notch at the fourth one.
+    let input = "
+v -1 2 0
+v 0 -1 0
+v 1 2 0
+v 0 1 0
+f 1 2 3 4
+v 0 0 0
+v 0 0 1
+v 1 0 1
+v 2 0 0.5
+v 1 0 0
+f 5 6 7 8 9
+";
+    let res = crate::load_obj(input).unwrap();
+    assert_eq!(
+        res.faces[0].triangulate(&res, Triangulation::Fan).unwrap(),
+        vec![[0, 1, 2], [0, 2, 3]]
+    );
+    assert_eq!(
+        res.faces[0]
+            .triangulate(&res, Triangulation::EarClipping)
+            .unwrap(),
+        vec![[3, 0, 1], [1, 2, 3]]
+    );
+
+    let triangles = res.triangulate(Triangulation::default()).unwrap();
+    assert_eq!(triangles.len(), 2);
+    assert_eq!(triangles[1].len(), 3);
+    let mut corners = triangles[1].iter().flatten().copied().collect::<Vec<_>>();
+    corners.sort();
+    corners.dedup();
+    assert_eq!(corners, vec![0, 1, 2, 3, 4]);
+
+    let invalid = crate::load_obj("v 0 0 0\nf 1 2 1 1\n").unwrap();
+    assert!(matches!(
+        invalid.triangulate(Triangulation::EarClipping),
+        Err(ValidationError::Index {
+            location: ElementLocation::Face {
+                face: 0,
+                element: 1
+            },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn generate_normals_test() {
+    let input = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 1
+vn 1 1 1
+s 1
+f 1 2 3//1
+f 1 3 4
+s off
+f 1 4 2
+";
+    let mut model = crate::load_obj(input).unwrap();
+    model.generate_normals().unwrap();
+    let indices = model
+        .faces
+        .iter()
+        .map(|f| {
+            f.elements
+                .iter()
+                .map(|e| e.normal_index.unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(indices, vec![vec![1, 2, 3], vec![1, 3, 4], vec![5, 5, 5]]);
+
+    let s = std::f32::consts::FRAC_1_SQRT_2;
+    let expected = [
+        [s, 0.0, s],
+        [0.0, 0.0, 1.0],
+        [s, 0.0, s],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    assert_eq!(model.normals.len(), expected.len());
+    for (n, e) in model.normals.iter().zip(expected) {
+        assert!(
+            (n.x - e[0]).abs() < 1e-6 && (n.y - e[1]).abs() < 1e-6 && (n.z - e[2]).abs() < 1e-6
+        );
+    }
+
+    let mut invalid = crate::load_obj("v 0 0 0\nvn 0 0 1\nf 1 1 3\n").unwrap();
+    assert!(invalid.generate_normals().is_err());
+    assert_eq!(invalid.normals.len(), 1);
+}
+
+#[test]
+fn generate_tangents_test() {
+    // The second triangle mirrors the texture, so the vertices it shares
+    // with the first one are split.
+    let input = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+f 1/1/1 3/3/1 4/2/1
+";
+    let model = crate::load_obj(input).unwrap();
+    let mut mesh = model.to_indexed_mesh().unwrap().remove(0);
+    assert_eq!(mesh.positions.len(), 4);
+    mesh.generate_tangents().unwrap();
+    assert_eq!(mesh.positions.len(), 6);
+    assert_eq!(mesh.textures.len(), 6);
+    assert_eq!(mesh.normals.len(), 6);
+    assert_eq!(mesh.indices, vec![0, 1, 2, 4, 5, 3]);
+    assert_eq!(mesh.positions[4], mesh.positions[0]);
+    assert_eq!(mesh.positions[5], mesh.positions[2]);
+    let expected = [
+        [1.0, 0.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0, 1.0],
+        [0.0, 1.0, 0.0, -1.0],
+        [0.0, 1.0, 0.0, -1.0],
+        [0.0, 1.0, 0.0, -1.0],
+    ];
+    for (t, e) in mesh.tangents.iter().zip(expected) {
+        assert!(t.iter().zip(e).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+
+    let flat = crate::load_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2/1 3/1\n").unwrap();
+    let mut mesh = flat.to_indexed_mesh().unwrap().remove(0);
+    assert_eq!(mesh.generate_tangents(), Err(TangentError::MissingNormals));
+    assert!(mesh.tangents.is_empty());
+}
+
+/// Feeds the triangles of an `IndexedMesh` to the reference MikkTSpace
+/// implementation.
+struct MikkTSpaceMesh<'a> {
+    mesh: &'a IndexedMesh,
+    tangents: Vec<[f32; 4]>,
+}
+
+impl MikkTSpaceMesh<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> usize {
+        self.mesh.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for MikkTSpaceMesh<'_> {
+    fn num_faces(&self) -> usize {
+        self.mesh.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.positions[self.vertex(face, vert)]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.normals[self.vertex(face, vert)]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.mesh.textures[self.vertex(face, vert)]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = tangent;
+    }
+}
+
+/// Builds a curved grid of triangles with the texture coordinates given by
+/// `uv` at each corner, once with shared vertices and once with separate
+/// copies of them for every triangle.
+fn tangent_grid(uv: impl Fn(usize, f32, f32) -> [f32; 2]) -> [String; 2] {
+    let n = 6;
+    let mut data = Vec::new();
+    for j in 0..=n {
+        for i in 0..=n {
+            let (x, y) = (i as f32 / n as f32 * 2.0 - 1.0, j as f32 / n as f32);
+            let z = (x * 1.3).sin() * 0.4 + y * y * 0.3;
+            let (dx, dy) = (0.52 * (x * 1.3).cos(), 0.6 * y);
+            let length = (dx * dx + dy * dy + 1.0).sqrt();
+            let [u, v] = uv(data.len(), x, y);
+            data.push(format!(
+                "v {} {} {}\nvn {} {} {}\nvt {} {}\n",
+                x,
+                y,
+                z,
+                -dx / length,
+                -dy / length,
+                1.0 / length,
+                u,
+                v
+            ));
+        }
+    }
+    let corner = |i: usize, j: usize| j * (n + 1) + i;
+    let triangles = (0..n)
+        .flat_map(|j| (0..n).map(move |i| (i, j)))
+        .flat_map(|(i, j)| {
+            let (a, b, c, d) = (
+                corner(i, j),
+                corner(i + 1, j),
+                corner(i + 1, j + 1),
+                corner(i, j + 1),
+            );
+            [[a, b, c], [a, c, d]]
+        })
+        .collect::<Vec<_>>();
+
+    let mut shared = data.concat();
+    let mut separate = String::new();
+    for (k, t) in triangles.iter().enumerate() {
+        let [a, b, c] = t.map(|v| v + 1);
+        shared.push_str(&format!("f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}\n"));
+        separate.push_str(&t.map(|v| data[v].as_str()).concat());
+        let [a, b, c] = [1, 2, 3].map(|i| 3 * k + i);
+        separate.push_str(&format!("f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}\n"));
+    }
+    [shared, separate]
+}
+
+#[test]
+fn generate_tangents_mikktspace_test() {
+    // A repeatable value between -1 and 1 for each vertex.
+    let noise = |v: usize, k: u32| {
+        let h = (v as u32 ^ k).wrapping_mul(0x9e37_79b9).rotate_left(13);
+        (h.wrapping_mul(0x85eb_ca6b) >> 8) as f32 / (1 << 23) as f32 - 1.0
+    };
+    let inputs = [
+        // Smooth, but mirrored along x = 0.
+        tangent_grid(|_, x, y| [x.abs(), y * 0.7 + x * 0.1]),
+        // Jittered by up to 0.2 on a step of about 0.3.
+        tangent_grid(|v, x, y| [x + 0.2 * noise(v, 1), y + 0.2 * noise(v, 2)]),
+        // Jittered and mirrored.
+        tangent_grid(|v, x, y| [x.abs() + 0.2 * noise(v, 3), y + 0.2 * noise(v, 4)]),
+        // Random, so many triangles are flipped.
+        tangent_grid(|v, _, _| [noise(v, 5), noise(v, 6)]),
+    ];
+
+    for input in inputs.iter().flatten() {
+        let model = crate::load_obj(input).unwrap();
+        let mesh = model.to_indexed_mesh().unwrap().remove(0);
+        let mut reference = MikkTSpaceMesh {
+            mesh: &mesh,
+            tangents: vec![[0.0; 4]; mesh.indices.len()],
+        };
+        assert!(bevy_mikktspace::generate_tangents(&mut reference));
+        let expected = reference.tangents;
+
+        let mut generated = mesh.clone();
+        generated.generate_tangents().unwrap();
+        assert_eq!(generated.tangents.len(), generated.positions.len());
+        for (c, (i, e)) in generated.indices.iter().zip(expected).enumerate() {
+            let i = *i as usize;
+            let original = mesh.indices[c] as usize;
+            assert_eq!(generated.positions[i], mesh.positions[original]);
+            assert_eq!(generated.textures[i], mesh.textures[original]);
+            assert_eq!(generated.normals[i], mesh.normals[original]);
+            assert_eq!(generated.tangents[i], e);
+        }
+    }
+}
+
+#[test]
+fn statistics_test() {
+    let input = "
+v 0 0 0
+v 2 0 0
+v 2 2 0
+v 0 2 0
+v 0 0 4
+v 9 9 9
+g base
+f 1 2 3 4
+o second
+g side
+f 1 2 5
+f 1 2 2
+l 1 5
+p 3
+";
+    let model = crate::load_obj(input).unwrap();
+
+    let stats = model.statistics().unwrap();
+    assert_eq!(
+        stats.bounding_box,
+        Some(BoundingBox {
+            min: [0.0, 0.0, 0.0],
+            max: [9.0, 9.0, 9.0]
+        })
+    );
+    assert_eq!(stats.surface_area, 8.0);
+    assert_eq!(
+        (stats.polygons, stats.triangles, stats.degenerate_faces),
+        (3, 4, 1)
+    );
+    assert_eq!((stats.lines, stats.points), (1, 1));
+
+    let base = model.group_statistics("base").unwrap();
+    assert_eq!(
+        base.bounding_box,
+        Some(BoundingBox {
+            min: [0.0, 0.0, 0.0],
+            max: [2.0, 2.0, 0.0]
+        })
+    );
+    assert_eq!(base.centroid, Some([1.0, 1.0, 0.0]));
+    let sphere = base.bounding_sphere.unwrap();
+    assert_eq!(sphere.center, [1.0, 1.0, 0.0]);
+    assert!((sphere.radius - std::f32::consts::SQRT_2).abs() < 1e-6);
+    assert_eq!(base.surface_area, 4.0);
+    assert_eq!((base.polygons, base.triangles, base.lines), (1, 2, 0));
+
+    let object = model.object_statistics(0).unwrap();
+    assert_eq!(object.bounding_box.map(|b| b.size()), Some([2.0, 2.0, 4.0]));
+    assert_eq!(object.surface_area, 4.0);
+    assert_eq!(
+        (object.polygons, object.degenerate_faces, object.points),
+        (2, 1, 1)
+    );
+
+    assert_eq!(model.group_statistics("missing"), Ok(Statistics::default()));
+    assert_eq!(model.object_statistics(1), Ok(Statistics::default()));
+}
+
+#[test]
+fn transform_test() {
+    let input = "
+v 1 2 3
+v 0 0 0 2
+v 1 0 0
+vn 0 1 0
+f 1//1 2//1 3//1
+";
+    let mut model = crate::load_obj(input).unwrap();
+    let t = Transform::y_up_to_z_up().then(&Transform::translation(1.0, 0.0, 0.0));
+    model.transform(&t).unwrap();
+    assert_eq!(model.vertices[0], Vertex::new(2.0, -3.0, 2.0, None));
+    assert_eq!(model.vertices[1], Vertex::new(1.0, 0.0, 0.0, Some(2.0)));
+    assert_eq!(
+        (model.normals[0].x, model.normals[0].y, model.normals[0].z),
+        (0.0, 0.0, 1.0)
+    );
+
+    model.transform(&Transform::flip_handedness()).unwrap();
+    assert_eq!(model.vertices[0], Vertex::new(2.0, -3.0, -2.0, None));
+    assert_eq!(model.normals[0].z, -1.0);
+    let order = model.faces[0]
+        .elements
+        .iter()
+        .map(|e| e.vertex_index)
+        .collect::<Vec<_>>();
+    assert_eq!(order, vec![1, 3, 2]);
+
+    let before = model.vertices.clone();
+    assert_eq!(
+        model.transform(&Transform::scale(0.0, 1.0, 1.0)),
+        Err(TransformError::Singular)
+    );
+    assert_eq!(model.vertices, before);
+
+    let mut model = crate::load_obj("v 1 1 1\nv 3 2 1\nv 5 1 2\n").unwrap();
+    model.recenter();
+    assert_eq!(model.vertices[0], Vertex::new(-2.0, -0.5, -0.5, None));
+    model.rescale(2.0).unwrap();
+    assert_eq!(model.vertices[2], Vertex::new(1.0, -0.25, 0.25, None));
+}
+
+#[test]
+fn exponent_without_fraction_test() {
+    let res = crate::load_obj("v 1e-05 0 2E3\n").unwrap();
+    assert_eq!(res.vertices, vec![Vertex::new(1e-5, 0.0, 2000.0, None)]);
+}
@@ -5,28 +5,40 @@ mod obj;
 mod test;
 
 use std::borrow::Cow;
-use std::iter::Enumerate;
+use std::fmt;
+use std::iter::{Cloned, Enumerate};
 use std::ops::Index;
-use std::ops::IndexMut;
+use std::slice::Iter;
 
 pub use mtl::parse_mtl;
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::digit1,
-    combinator::{map, opt},
-    error::Error,
-    multi::{fold_many0, fold_many1},
-    Input, Parser,
+    bytes::complete::{tag, take_while},
+    character::complete::{digit0, digit1, line_ending, multispace1, satisfy, space0},
+    combinator::{eof, opt, recognize},
+    error::{Error, ErrorKind},
+    IResult, Input, Parser,
 };
 pub use obj::parse_obj;
 
 use thiserror::Error;
 
+use crate::{Diagnostic, Severity};
+
+/// An error produced while splitting the input text into tokens.
 #[derive(Error, Debug)]
 pub enum TokenizeError {
-    #[error("Parse Error: `{0}`")]
-    Parse(String),
+    #[error("Parse Error at {span}: `{message}`")]
+    Parse { message: String, span: Span },
+}
+
+impl TokenizeError {
+    /// The position in the input the error occurred at.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Parse { span, .. } => *span,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -80,12 +92,15 @@ pub enum Token<'a> {
     Object,
 
     /// g group_name1 group_name2 ...
-    /// Currently ignores all names after the first
     Group,
 
     /// s group_number
     Smoothing,
 
+    /// mg group_number [res]
+    /// The resolution is required unless merging is turned off
+    MergingGroup,
+
     /// bevel on/off
     Bevel,
 
@@ -111,6 +126,58 @@ pub enum Token<'a> {
     /// usemap map_name/off
     UseTextureMap,
 
+    /// ctech technique resolution
+    /// Technique is one of cparm, cspace or curv
+    CurveTechnique,
+
+    /// stech technique resolution
+    /// Technique is one of cparma, cparmb, cspace or curv
+    SurfaceTechnique,
+
+    /// cstype [rat] type
+    /// Type is one of bmatrix, bezier, bspline, cardinal or taylor
+    CurveSurfaceType,
+
+    /// deg degu [degv]
+    Degree,
+
+    /// bmat u/v matrix
+    BasisMatrix,
+
+    /// step stepu [stepv]
+    Step,
+
+    /// curv u0 u1 v1 v2 ...
+    Curve,
+
+    /// curv2 vp1 vp2 ...
+    Curve2,
+
+    /// surf s0 s1 t0 t1 v1/vt1/vn1 v2/vt2/vn2 ...
+    Surface,
+
+    /// parm u/v p1 p2 p3 ...
+    Parameter,
+
+    /// trim u0 u1 curv2d u0 u1 curv2d ...
+    Trim,
+
+    /// hole u0 u1 curv2d u0 u1 curv2d ...
+    Hole,
+
+    /// scrv u0 u1 curv2d u0 u1 curv2d ...
+    SpecialCurve,
+
+    /// sp vp1 vp2 ...
+    SpecialPoint,
+
+    /// end
+    /// Closes the body of a free-form curve or surface
+    End,
+
+    /// con surf_1 q0_1 q1_1 curv2d_1 surf_2 q0_2 q1_2 curv2d_2
+    Connect,
+
     /// Used in Ka/Kd/Ks
     Spectral,
 
@@ -466,62 +533,243 @@ pub enum Token<'a> {
     OptionTextureResolution,
 }
 
-#[derive(Debug, Clone)]
-pub struct TokenSet<'a> {
-    tokens: Vec<Token<'a>>,
+impl Token<'_> {
+    /// The keyword this token was read from, if it is one.
+    pub fn keyword(&self) -> Option<&'static str> {
+        let kw = match self {
+            Token::Ignore | Token::String(_) | Token::Float(_) | Token::Int(_) => return None,
+            Token::Slash => "/",
+            Token::Vertex => "v",
+            Token::VertexNormal => "vn",
+            Token::VertexTexture => "vt",
+            Token::VertexParam => "vp",
+            Token::Face => "f",
+            Token::Point => "p",
+            Token::Line => "l",
+            Token::MaterialLib => "mtllib",
+            Token::UseMaterial => "usemtl",
+            Token::Object => "o",
+            Token::Group => "g",
+            Token::Smoothing => "s",
+            Token::MergingGroup => "mg",
+            Token::Bevel => "bevel",
+            Token::CInterp => "c_interp",
+            Token::DInterp => "d_interp",
+            Token::Lod => "lod",
+            Token::ShadowObj => "shadow_obj",
+            Token::TraceObj => "trace_obj",
+            Token::TextureMapLib => "maplib",
+            Token::UseTextureMap => "usemap",
+            Token::CurveTechnique => "ctech",
+            Token::SurfaceTechnique => "stech",
+            Token::CurveSurfaceType => "cstype",
+            Token::Degree => "deg",
+            Token::BasisMatrix => "bmat",
+            Token::Step => "step",
+            Token::Curve => "curv",
+            Token::Curve2 => "curv2",
+            Token::Surface => "surf",
+            Token::Parameter => "parm",
+            Token::Trim => "trim",
+            Token::Hole => "hole",
+            Token::SpecialCurve => "scrv",
+            Token::SpecialPoint => "sp",
+            Token::End => "end",
+            Token::Connect => "con",
+            Token::Spectral => "spectral",
+            Token::Xyz => "xyz",
+            Token::NewMaterial => "newmtl",
+            Token::AmbientColor => "Ka",
+            Token::DiffuseColor => "Kd",
+            Token::SpecularColor => "Ks",
+            Token::EmissiveCoefficient => "Ke",
+            Token::SpecularExponent => "Ns",
+            Token::Disolved => "d",
+            Token::Halo => "-halo",
+            Token::Transparancy => "Tr",
+            Token::TransmissionFactor => "Tf",
+            Token::Sharpness => "sharpness",
+            Token::IndexOfRefraction => "Ni",
+            Token::IlluminationModel => "illum",
+            Token::TextureMapAmbient => "map_Ka",
+            Token::TextureMapDiffuse => "map_Kd",
+            Token::TextureMapSpecular => "map_Ks",
+            Token::TextureMapShininess => "map_Ns",
+            Token::TextureMapDisolved => "map_d",
+            Token::AntiAliasMap => "map_aat",
+            Token::DisplacementMap => "disp",
+            Token::Decal => "decal",
+            Token::BumpMap => "bump",
+            Token::ReflectionMap => "refl",
+            Token::ReflectionType => "-type",
+            Token::OptionBlendU => "-blendu",
+            Token::OptionBlendV => "-blendv",
+            Token::OptionBumpMultiplier => "-bm",
+            Token::OptionBoost => "-boost",
+            Token::OptionColorCorrect => "-cc",
+            Token::OptionClamp => "-clamp",
+            Token::OptionIMFChan => "-imfchan",
+            Token::OptionRange => "-mm",
+            Token::OptionOffset => "-o",
+            Token::OptionScale => "-s",
+            Token::OptionTurbulence => "-t",
+            Token::OptionTextureResolution => "-texres",
+        };
+        Some(kw)
+    }
 }
 
-impl TokenSet<'_> {
-    pub fn is_empty(&self) -> bool {
-        self.tokens.is_empty()
+/// A position in the source text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column number, counted in characters and starting at 1.
+    pub column: usize,
+}
+
+impl Span {
+    fn advance(&mut self, consumed: &str) {
+        self.offset += consumed.len();
+        for c in consumed.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
     }
+}
 
-    pub fn split_at(&self, index: usize) -> (Self, Self) {
-        let (a, b) = self.tokens.split_at(index);
-        (Self { tokens: a.to_vec() }, Self { tokens: b.to_vec() })
+impl Default for Span {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
     }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
 
+/// The output of the tokenizers: every token along with the position in the
+/// source text it was read from.
+#[derive(Clone, Debug, Default)]
+pub struct Tokens<'a> {
+    tokens: Vec<Token<'a>>,
+    spans: Vec<Span>,
+    lines: Vec<usize>,
+    end: Span,
+}
+
+#[cfg(test)]
+impl Tokens<'_> {
     pub fn len(&self) -> usize {
         self.tokens.len()
     }
+
+    pub fn span(&self, index: usize) -> Span {
+        self.spans[index]
+    }
 }
 
-impl<'a> Index<usize> for TokenSet<'a> {
+impl<'a> Index<usize> for Tokens<'a> {
     type Output = Token<'a>;
     fn index(&self, index: usize) -> &Self::Output {
         &self.tokens[index]
     }
 }
 
-impl IndexMut<usize> for TokenSet<'_> {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.tokens[index]
-    }
+/// A borrowed view over a tokenized file.
+///
+/// This is the input type for the model and material parsers. It is a
+/// cheap, `Copy` window into the token buffer produced by the tokenizers, so
+/// consuming a token is just a matter of advancing the start of the slice.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenSet<'a> {
+    tokens: &'a [Token<'a>],
+    spans: &'a [Span],
+    lines: &'a [usize],
+    end: Span,
 }
 
-impl<'a> From<Vec<Token<'a>>> for TokenSet<'a> {
-    fn from(tokens: Vec<Token<'a>>) -> Self {
-        Self { tokens }
+impl TokenSet<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
     }
-}
 
-impl<'a> AsRef<Vec<Token<'a>>> for TokenSet<'a> {
-    fn as_ref(&self) -> &Vec<Token<'a>> {
-        &self.tokens
+    pub fn split_at(&self, index: usize) -> (Self, Self) {
+        let (a, b) = self.tokens.split_at(index);
+        let (a_spans, b_spans) = self.spans.split_at(index);
+        let (a_lines, b_lines) = self.lines.split_at(index);
+        (
+            Self {
+                tokens: a,
+                spans: a_spans,
+                lines: a_lines,
+                end: b_spans.first().copied().unwrap_or(self.end),
+            },
+            Self {
+                tokens: b,
+                spans: b_spans,
+                lines: b_lines,
+                end: self.end,
+            },
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// The position of the first token in the set, or the end of the input
+    /// if the set is empty.
+    pub fn position(&self) -> Span {
+        self.spans.first().copied().unwrap_or(self.end)
+    }
+
+    /// Splits off the tokens which are on the same line as the first token.
+    /// Lines joined with a `\` count as one.
+    pub fn split_line(&self) -> (Self, Self) {
+        let count = self.lines.first().map_or(0, |line| {
+            self.lines
+                .iter()
+                .position(|l| l != line)
+                .unwrap_or(self.len())
+        });
+        self.split_at(count)
     }
 }
 
-impl<'a> Iterator for TokenSet<'a> {
-    type Item = Token<'a>;
+impl<'a> Index<usize> for TokenSet<'a> {
+    type Output = Token<'a>;
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.tokens[index]
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.tokens.first().cloned()
+impl<'a> From<&'a Tokens<'a>> for TokenSet<'a> {
+    fn from(tokens: &'a Tokens<'a>) -> Self {
+        Self {
+            tokens: &tokens.tokens,
+            spans: &tokens.spans,
+            lines: &tokens.lines,
+            end: tokens.end,
+        }
     }
 }
 
 impl<'a> Input for TokenSet<'a> {
     type Item = Token<'a>;
-    type Iter = TokenSet<'a>;
+    type Iter = Cloned<Iter<'a, Token<'a>>>;
     type IterIndices = Enumerate<Self::Iter>;
 
     fn input_len(&self) -> usize {
@@ -529,19 +777,16 @@ impl<'a> Input for TokenSet<'a> {
     }
 
     fn take(&self, index: usize) -> Self {
-        Self {
-            tokens: self.tokens.iter().take(index).cloned().collect(),
-        }
+        self.split_at(index).0
     }
 
     fn take_from(&self, index: usize) -> Self {
-        Self {
-            tokens: self.tokens[index..].to_vec(),
-        }
+        self.split_at(index).1
     }
 
     fn take_split(&self, index: usize) -> (Self, Self) {
-        self.split_at(index)
+        let (prefix, suffix) = self.split_at(index);
+        (suffix, prefix)
     }
 
     fn position<P>(&self, predicate: P) -> Option<usize>
@@ -552,7 +797,7 @@ impl<'a> Input for TokenSet<'a> {
     }
 
     fn iter_elements(&self) -> Self::Iter {
-        self.clone()
+        self.tokens.iter().cloned()
     }
 
     fn iter_indices(&self) -> Self::IterIndices {
@@ -568,110 +813,160 @@ impl<'a> Input for TokenSet<'a> {
     }
 }
 
-fn parse_digit<'a>() -> impl Parser<&'a str, Output = Token<'a>, Error = Error<&'a str>> {
-    map(
-        (
-            opt(alt((tag("+"), tag("-")))),
-            fold_many1(digit1, Vec::new, |mut acc: Vec<_>, item| {
-                acc.push(item);
-                acc
-            }),
-        ),
-        |(sign, s): (Option<&str>, Vec<&str>)| {
-            let mut val = s.join("").parse::<i32>().unwrap_or_default();
-            if sign == Some("-") {
-                val *= -1;
-            }
-            Token::Int(val)
+/// Parses the statement on the first line of `input`, which has to consume
+/// the whole line.
+///
+/// Returns the remaining lines along with either the parsed statement, or a
+/// diagnostic describing the statement and the position at which it went
+/// wrong.
+pub fn parse_statement<'a, P>(
+    parser: &mut P,
+    input: TokenSet<'a>,
+) -> (TokenSet<'a>, Result<P::Output, Diagnostic>)
+where
+    P: Parser<TokenSet<'a>, Error = Error<TokenSet<'a>>>,
+{
+    let (line, remainder) = input.split_line();
+    let span = match parser.parse_complete(line) {
+        Ok((rest, output)) if rest.is_empty() => return (remainder, Ok(output)),
+        Ok((rest, _)) => rest.position(),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => e.input.position(),
+        Err(nom::Err::Incomplete(_)) => line.position(),
+    };
+    let message = match &line[0] {
+        Token::String(s) => format!("Unrecognized statement: `{}`", s),
+        token => match token.keyword() {
+            Some(kw) => format!("Invalid `{}` statement", kw),
+            None => format!("Unexpected token: `{:?}`", token),
         },
-    )
+    };
+    let diagnostic = Diagnostic::new(Severity::Error, message, span, line[0].keyword());
+    (remainder, Err(diagnostic))
+}
+
+/// Runs `parser` over the whole input, recording the position of every token
+/// it produces. `Token::Ignore` results are dropped.
+///
+/// Along with the input, the parser is told whether it is at the start of a
+/// line, with nothing but whitespace and comments before it. A `\` at the
+/// end of a line joins the next line onto it.
+fn tokenize<'a, P>(input: &'a str, mut parser: P) -> Result<Tokens<'a>, TokenizeError>
+where
+    P: FnMut(&'a str, bool) -> IResult<&'a str, Token<'a>>,
+{
+    let mut res = Tokens::default();
+    let mut position = Span::default();
+    let mut line = 0;
+    let mut line_start = true;
+    let mut remaining = input;
+    while !remaining.is_empty() {
+        if let Ok((rest, consumed)) = line_continuation(remaining) {
+            position.advance(consumed);
+            remaining = rest;
+            continue;
+        }
+        let (rest, token) = match parser(remaining, line_start) {
+            Ok((rest, _)) if rest.len() == remaining.len() => {
+                return Err(TokenizeError::Parse {
+                    message: "Tokenizer made no progress".into(),
+                    span: position,
+                })
+            },
+            Ok(res) => res,
+            Err(_) => {
+                let line = remaining.lines().next().unwrap_or_default();
+                return Err(TokenizeError::Parse {
+                    message: format!("Unrecognized input: `{}`", line),
+                    span: position,
+                });
+            },
+        };
+        let consumed = &remaining[..remaining.len() - rest.len()];
+        if !matches!(token, Token::Ignore) {
+            res.tokens.push(token);
+            res.spans.push(position);
+            res.lines.push(line);
+            line_start = false;
+        }
+        if consumed.contains(['\n', '\r']) {
+            line += 1;
+            line_start = true;
+        }
+        position.advance(consumed);
+        remaining = rest;
+    }
+    res.end = position;
+    Ok(res)
+}
+
+/// Matches a `\` followed by the end of the line, which joins the lines
+/// around it.
+fn line_continuation(input: &str) -> IResult<&str, &str> {
+    recognize((tag("\\"), space0, line_ending)).parse_complete(input)
+}
+
+/// Matches a statement or option keyword, which has to be followed by
+/// whitespace or the end of the input. The word is looked up with `kw_map`,
+/// and anything it doesn't recognise is left for the other token parsers.
+fn parse_keyword<'a>(
+    kw_map: fn(&'a str) -> Token<'a>,
+) -> impl Parser<&'a str, Output = Token<'a>, Error = Error<&'a str>> {
+    move |input: &'a str| {
+        let (rest, (first, tail)) = (
+            satisfy(|c: char| c.is_alphabetic() || c == '_' || c == '-'),
+            take_while(|c: char| c.is_alphanumeric() || c == '_' || c == '-'),
+        )
+            .parse(input)?;
+        let word = &input[..first.len_utf8() + tail.len()];
+        match kw_map(word) {
+            Token::Ignore => Err(nom::Err::Error(Error::new(input, ErrorKind::Tag))),
+            token => {
+                let (rest, _) = alt((multispace1, eof)).parse(rest)?;
+                Ok((rest, token))
+            },
+        }
+    }
+}
+
+fn parse_digit<'a>() -> impl Parser<&'a str, Output = Token<'a>, Error = Error<&'a str>> {
+    |input: &'a str| {
+        let (rest, _) = (opt(alt((tag("+"), tag("-")))), digit1).parse(input)?;
+        let digits = &input[..input.len() - rest.len()];
+        // Values too large for an index are still usable as coordinates.
+        match digits.parse::<i32>() {
+            Ok(i) => Ok((rest, Token::Int(i))),
+            Err(_) => Ok((
+                rest,
+                Token::Float(digits.parse::<f32>().unwrap_or(f32::NAN)),
+            )),
+        }
+    }
 }
 
-#[allow(clippy::type_complexity)]
 fn parse_float<'a>() -> impl Parser<&'a str, Output = Token<'a>, Error = Error<&'a str>> {
-    map(
-        (
+    |input: &'a str| {
+        let exponent = || {
+            (
+                alt((tag("e"), tag("E"))),
+                opt(alt((tag("+"), tag("-")))),
+                digit1,
+            )
+        };
+        // The fractional part can be left out when there is an exponent, as
+        // in the `1e-05` written by `%g`.
+        let (rest, _) = (
             opt(alt((tag("+"), tag("-")))),
             alt((
-                map(
-                    (
-                        fold_many0(digit1, Vec::new, |mut acc: Vec<_>, item| {
-                            acc.push(item);
-                            acc
-                        }),
-                        tag("."),
-                        opt(fold_many1(digit1, Vec::new, |mut acc: Vec<_>, item| {
-                            acc.push(item);
-                            acc
-                        })),
-                        opt(map(
-                            (
-                                alt((tag("e"), tag("E"))),
-                                opt(alt((tag("+"), tag("-")))),
-                                digit1,
-                            ),
-                            |(e, sign, digits)| {
-                                let mut acc = String::new();
-                                acc.push_str(e);
-                                if let Some(sign) = sign {
-                                    acc.push_str(sign);
-                                }
-                                acc.push_str(digits);
-                                acc
-                            },
-                        )),
-                    ),
-                    |(f, _, s, e)| (f, s.unwrap_or_default(), e.unwrap_or_default()),
-                ),
-                map(
-                    (
-                        opt(fold_many1(digit1, Vec::new, |mut acc: Vec<_>, item| {
-                            acc.push(item);
-                            acc
-                        })),
-                        tag("."),
-                        fold_many1(digit1, Vec::new, |mut acc: Vec<_>, item| {
-                            acc.push(item);
-                            acc
-                        }),
-                        opt(map(
-                            (
-                                alt((tag("e"), tag("E"))),
-                                opt(alt((tag("+"), tag("-")))),
-                                digit1,
-                            ),
-                            |(e, sign, digits)| {
-                                let mut acc = String::new();
-                                acc.push_str(e);
-                                if let Some(sign) = sign {
-                                    acc.push_str(sign);
-                                }
-                                acc.push_str(digits);
-                                acc
-                            },
-                        )),
-                    ),
-                    |(f, _, s, e)| (f.unwrap_or_default(), s, e.unwrap_or_default()),
-                ),
+                recognize((digit0, tag("."), digit0, opt(exponent()))),
+                recognize((digit1, exponent())),
             )),
-        ),
-        |(sign, (f, s, e)): (Option<&str>, (Vec<&str>, Vec<&str>, String))| {
-            let mut acc = Vec::new();
-            if !f.is_empty() {
-                acc.extend(f);
-            }
-            acc.push(".");
-            if !s.is_empty() {
-                acc.extend(s);
-            }
-            if !e.is_empty() {
-                acc.push(e.as_str());
-            }
-            let mut val = acc.join("").parse::<f32>().unwrap_or_default();
-            if sign == Some("-") {
-                val *= -1.0;
-            }
-            Token::Float(val)
-        },
-    )
+        )
+            .parse(input)?;
+        let digits = &input[..input.len() - rest.len()];
+        match digits.parse::<f32>() {
+            Ok(f) => Ok((rest, Token::Float(f))),
+            // A lone `.` without any digits isn't a number.
+            Err(_) => Err(nom::Err::Error(Error::new(input, ErrorKind::Float))),
+        }
+    }
 }
@@ -3,92 +3,28 @@ use std::result::Result;
 
 use nom::{
     branch::alt,
-    bytes::{is_not, tag, tag_no_case, take_till},
-    character::{
-        complete::{line_ending, multispace1},
-        multispace0,
-    },
+    bytes::{is_not, tag, take_till},
+    character::complete::{line_ending, multispace1},
     combinator::map,
-    multi::fold_many0,
-    sequence::{delimited, preceded},
+    sequence::preceded,
     Parser,
 };
 
-use super::{Token, TokenSet, TokenizeError};
+use super::{Token, TokenizeError, Tokens};
 
-pub fn parse_mtl(input: &str) -> Result<TokenSet, TokenizeError> {
-    match fold_many0(
-        alt((
-            delimited(
-                multispace0(),
-                map(
-                    alt([
-                        tag_no_case("newmtl"),
-                        tag_no_case("spectral"),
-                        tag_no_case("xyz"),
-                        tag_no_case("sharpness"),
-                        tag_no_case("illum"),
-                        tag_no_case("map_disp"),
-                        tag_no_case("map_Ka"),
-                        tag_no_case("map_Kd"),
-                        tag_no_case("map_Ks"),
-                        tag_no_case("map_Ns"),
-                        tag_no_case("map_aat"),
-                        tag_no_case("map_d"),
-                        tag_no_case("disp"),
-                        tag_no_case("decal"),
-                        tag_no_case("bump"),
-                        tag_no_case("refl"),
-                        tag_no_case("-halo"),
-                        tag_no_case("-type"),
-                        tag_no_case("-texres"),
-                        tag_no_case("-blendu"),
-                        tag_no_case("-blendv"),
-                        tag_no_case("-boost"),
-                        tag_no_case("-clamp"),
-                        tag_no_case("-imfchan"),
-                        tag_no_case("-bm"),
-                        tag_no_case("-cc"),
-                        tag_no_case("-mm"),
-                        tag_no_case("-o"),
-                        tag_no_case("-s"),
-                        tag_no_case("-t"),
-                        tag_no_case("ka"),
-                        tag_no_case("kd"),
-                        tag_no_case("ks"),
-                        tag_no_case("ke"),
-                        tag_no_case("ns"),
-                        tag_no_case("tr"),
-                        tag_no_case("Tf"),
-                        tag_no_case("Ni"),
-                        tag_no_case("d"),
-                    ]),
-                    kw_map,
-                ),
-                map(multispace1, |_| Token::Ignore),
-            ),
-            super::parse_float(),
-            super::parse_digit(),
-            map(
-                preceded(tag("#"), take_till(|c| c == '\n' || c == '\r')),
-                |_| Token::Ignore,
-            ),
-            map(alt((line_ending, multispace1)), |_| Token::Ignore),
-            map(is_not(" \r\n"), |s: &str| Token::String(Cow::Borrowed(s))),
-        )),
-        Vec::new,
-        |mut acc: Vec<Token>, item| {
-            if item != Token::Ignore {
-                acc.push(item);
-            }
-            acc
-        },
-    )
-    .parse_complete(input)
-    {
-        Ok((_, v)) => Ok(v.into()),
-        Err(e) => Err(TokenizeError::Parse(e.to_string())),
-    }
+pub fn parse_mtl(input: &str) -> Result<Tokens<'_>, TokenizeError> {
+    let mut parser = alt((
+        super::parse_keyword(kw_map),
+        super::parse_float(),
+        super::parse_digit(),
+        map(
+            preceded(tag("#"), take_till(|c| c == '\n' || c == '\r')),
+            |_| Token::Ignore,
+        ),
+        map(alt((line_ending, multispace1)), |_| Token::Ignore),
+        map(is_not(" \r\n"), |s: &str| Token::String(Cow::Borrowed(s))),
+    ));
+    super::tokenize(input, move |input, _| parser.parse_complete(input))
 }
 
 fn kw_map(value: &str) -> Token<'_> {
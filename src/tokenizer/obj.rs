@@ -3,74 +3,59 @@ use std::result::Result;
 
 use nom::{
     branch::alt,
-    bytes::{complete::is_not, tag, tag_no_case, take_till},
-    character::{
-        complete::{line_ending, multispace1},
-        multispace0,
-    },
+    bytes::{complete::is_not, tag, take_till, take_till1},
+    character::complete::{line_ending, multispace1},
     combinator::map,
-    multi::fold_many0,
-    sequence::{delimited, preceded},
+    sequence::preceded,
     Parser,
 };
 
-use super::{Token, TokenSet, TokenizeError};
+use super::{Token, TokenizeError, Tokens};
 
-pub fn parse_obj(input: &str) -> Result<TokenSet, TokenizeError> {
-    match fold_many0(
-        alt((
-            delimited(
-                multispace0(),
-                map(
-                    alt([
-                        tag_no_case("mtllib"),
-                        tag_no_case("usemtl"),
-                        tag_no_case("bevel"),
-                        tag_no_case("c_interp"),
-                        tag_no_case("d_interp"),
-                        tag_no_case("lod"),
-                        tag_no_case("shadow_obj"),
-                        tag_no_case("trace_obj"),
-                        tag_no_case("maplib"),
-                        tag_no_case("usemap"),
-                        tag_no_case("vt"),
-                        tag_no_case("vn"),
-                        tag_no_case("vp"),
-                        tag_no_case("v"),
-                        tag_no_case("f"),
-                        tag_no_case("l"),
-                        tag_no_case("p"),
-                        tag_no_case("o"),
-                        tag_no_case("g"),
-                        tag_no_case("s"),
-                    ]),
-                    kw_map,
-                ),
-                map(multispace1, |_| Token::Ignore),
-            ),
-            map(tag("/"), |_| Token::Slash),
-            super::parse_float(),
-            super::parse_digit(),
-            map(
-                preceded(tag("#"), take_till(|c| c == '\n' || c == '\r')),
-                |_| Token::Ignore,
-            ),
-            map(alt((line_ending, multispace1)), |_| Token::Ignore),
-            map(is_not("\r\n"), |s: &str| Token::String(Cow::Borrowed(s))),
-        )),
-        Vec::new,
-        |mut acc: Vec<Token>, item| {
-            if item != Token::Ignore {
-                acc.push(item);
-            }
-            acc
-        },
-    )
-    .parse_complete(input)
-    {
-        Ok((_, v)) => Ok(v.into()),
-        Err(e) => Err(TokenizeError::Parse(e.to_string())),
-    }
+pub fn parse_obj(input: &str) -> Result<Tokens<'_>, TokenizeError> {
+    let mut keyword = super::parse_keyword(kw_map);
+    let mut token = alt((
+        map(tag("/"), |_| Token::Slash),
+        super::parse_float(),
+        super::parse_digit(),
+        map(
+            preceded(tag("#"), take_till(|c| c == '\n' || c == '\r')),
+            |_| Token::Ignore,
+        ),
+        map(alt((line_ending, multispace1)), |_| Token::Ignore),
+        map(is_not(" \t\r\n"), |s: &str| Token::String(Cow::Borrowed(s))),
+    ));
+    let mut name = map(take_till1(|c| c == '\n' || c == '\r'), |s: &str| {
+        Token::String(Cow::Borrowed(s.trim_end()))
+    });
+    let mut name_next = false;
+    super::tokenize(input, move |input, line_start| {
+        // Statement keywords are only matched at the start of a line, so the
+        // same words can still be used as names and arguments.
+        let res = if line_start {
+            keyword
+                .parse_complete(input)
+                .or_else(|_| token.parse_complete(input))
+        } else if name_next {
+            name.parse_complete(input)
+        } else {
+            token.parse_complete(input)
+        };
+        // Names and file names take up the rest of the line, so they can
+        // contain spaces.
+        name_next = matches!(
+            res,
+            Ok((
+                _,
+                Token::MaterialLib
+                    | Token::UseMaterial
+                    | Token::Object
+                    | Token::ShadowObj
+                    | Token::TraceObj
+            ))
+        );
+        res
+    })
 }
 
 fn kw_map(value: &str) -> Token<'_> {
@@ -87,6 +72,7 @@ fn kw_map(value: &str) -> Token<'_> {
         "o" => Token::Object,
         "g" => Token::Group,
         "s" => Token::Smoothing,
+        "mg" => Token::MergingGroup,
         "bevel" => Token::Bevel,
         "c_interp" => Token::CInterp,
         "d_interp" => Token::DInterp,
@@ -95,6 +81,22 @@ fn kw_map(value: &str) -> Token<'_> {
         "trace_obj" => Token::TraceObj,
         "maplib" => Token::TextureMapLib,
         "usemap" => Token::UseTextureMap,
+        "ctech" => Token::CurveTechnique,
+        "stech" => Token::SurfaceTechnique,
+        "cstype" => Token::CurveSurfaceType,
+        "deg" => Token::Degree,
+        "bmat" => Token::BasisMatrix,
+        "step" => Token::Step,
+        "curv" => Token::Curve,
+        "curv2" => Token::Curve2,
+        "surf" => Token::Surface,
+        "parm" => Token::Parameter,
+        "trim" => Token::Trim,
+        "hole" => Token::Hole,
+        "scrv" => Token::SpecialCurve,
+        "sp" => Token::SpecialPoint,
+        "end" => Token::End,
+        "con" => Token::Connect,
         _ => Token::Ignore,
     }
 }
@@ -41,3 +41,64 @@ parse_float_test!(float_test_2_pos, "+1.", Token::Float(1.0));
 parse_float_test!(float_test_neg, "-1.1", Token::Float(-1.1));
 parse_float_test!(float_test_1_neg, "-.1", Token::Float(-0.1));
 parse_float_test!(float_test_2_neg, "-1.", Token::Float(-1.0));
+parse_float_test!(float_test_exp, "1.5e2", Token::Float(150.0));
+parse_float_test!(float_test_exp_no_fraction, "1e-05", Token::Float(1e-5));
+parse_float_test!(
+    float_test_exp_no_fraction_pos,
+    "+2E+3",
+    Token::Float(2000.0)
+);
+
+#[test]
+fn keyword_only_at_line_start_test() {
+    let tokens = super::parse_obj("g v surf\n  curv 0 1 1 2\n").unwrap();
+    assert_eq!(
+        tokens.tokens,
+        vec![
+            Token::Group,
+            Token::String("v".into()),
+            Token::String("surf".into()),
+            Token::Curve,
+            Token::Int(0),
+            Token::Int(1),
+            Token::Int(1),
+            Token::Int(2),
+        ]
+    );
+}
+
+#[test]
+fn name_rest_of_line_test() {
+    let tokens = super::parse_obj("usemtl My Material \no 1 and 2\ng a b\n").unwrap();
+    assert_eq!(
+        tokens.tokens,
+        vec![
+            Token::UseMaterial,
+            Token::String("My Material".into()),
+            Token::Object,
+            Token::String("1 and 2".into()),
+            Token::Group,
+            Token::String("a".into()),
+            Token::String("b".into()),
+        ]
+    );
+}
+
+#[test]
+fn line_continuation_test() {
+    let tokens = super::parse_obj("curv 0 1 \\\n  1 2\\\r\n v\nend").unwrap();
+    assert_eq!(
+        tokens.tokens,
+        vec![
+            Token::Curve,
+            Token::Int(0),
+            Token::Int(1),
+            Token::Int(1),
+            Token::Int(2),
+            Token::String("v".into()),
+            Token::End,
+        ]
+    );
+    assert_eq!(tokens.lines, [0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(tokens.span(3).line, 2);
+}
@@ -0,0 +1,179 @@
+use std::result::Result;
+
+use derive_more::{Constructor, From, Into};
+use thiserror::Error;
+
+use crate::{
+    math::{cross, dot, normalize},
+    stats::bounding_box,
+    Model,
+};
+
+/// The error produced when a transform can't be applied to a model.
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransformError {
+    /// Normals can't be transformed by a matrix which flattens space, as
+    /// it has no inverse.
+    #[error("The transform can't be inverted")]
+    Singular,
+}
+
+/// A 4x4 matrix, stored as rows, which transforms points written as
+/// columns.
+#[derive(Copy, Clone, Constructor, Debug, From, Into, PartialEq)]
+pub struct Transform {
+    /// The rows of the matrix.
+    pub matrix: [[f32; 4]; 4],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::scale(1.0, 1.0, 1.0)
+    }
+}
+
+impl Transform {
+    /// A transform which moves points by the given amounts.
+    pub fn translation(x: f32, y: f32, z: f32) -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, x],
+            [0.0, 1.0, 0.0, y],
+            [0.0, 0.0, 1.0, z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// A transform which scales points about the origin along each axis.
+    pub fn scale(x: f32, y: f32, z: f32) -> Self {
+        Self::new([
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Converts from a tool where y points up to one where z does, by
+    /// rotating about the x axis. The handedness is unchanged.
+    pub fn y_up_to_z_up() -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, -1.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Converts from a tool where z points up to one where y does, by
+    /// rotating about the x axis. The handedness is unchanged.
+    pub fn z_up_to_y_up() -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, -1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Converts between right and left handed coordinates by negating z.
+    pub fn flip_handedness() -> Self {
+        Self::scale(1.0, 1.0, -1.0)
+    }
+
+    /// The transform which applies `self`, then `next`.
+    pub fn then(&self, next: &Transform) -> Self {
+        let (a, b) = (&next.matrix, &self.matrix);
+        Self::new(
+            [0, 1, 2, 3].map(|r| [0, 1, 2, 3].map(|c| (0..4).map(|k| a[r][k] * b[k][c]).sum())),
+        )
+    }
+
+    /// The rows of the upper left 3x3 part of the matrix.
+    fn linear(&self) -> [[f32; 3]; 3] {
+        let m = &self.matrix;
+        [0, 1, 2].map(|r| [m[r][0], m[r][1], m[r][2]])
+    }
+}
+
+impl Model {
+    /// Applies `transform` to the vertices and normals of the model.
+    ///
+    /// Vertices are transformed as homogeneous points, with their weight
+    /// as the fourth coordinate, or 1 when they don't have one. An affine
+    /// transform leaves the weights unchanged. Normals are transformed by
+    /// the inverse-transpose of the upper left 3x3 part of the matrix, so
+    /// they stay perpendicular to the surface, and are normalized.
+    ///
+    /// When the transform mirrors the model, the order of the elements of
+    /// every face is reversed after the first one, so the faces still wind
+    /// counter-clockwise around their normals. Free-form surfaces are not
+    /// reoriented. Nothing is changed if the transform can't be inverted.
+    pub fn transform(&mut self, transform: &Transform) -> Result<(), TransformError> {
+        let [r0, r1, r2] = transform.linear();
+        let det = dot(r0, cross(r1, r2));
+        if det == 0.0 || !det.is_finite() {
+            return Err(TransformError::Singular);
+        }
+        // The rows of the inverse-transpose, up to a positive scale which
+        // normalizing removes.
+        let normal_rows =
+            [cross(r1, r2), cross(r2, r0), cross(r0, r1)].map(|r| r.map(|x| x * det.signum()));
+
+        let m = &transform.matrix;
+        for v in &mut self.vertices {
+            let w = v.w.unwrap_or(1.0);
+            let p = [v.x * w, v.y * w, v.z * w, w];
+            let [x, y, z, w] = m.map(|r| (0..4).map(|i| r[i] * p[i]).sum::<f32>());
+            (v.x, v.y, v.z) = (x / w, y / w, z / w);
+            if v.w.is_some() {
+                v.w = Some(w);
+            }
+        }
+        for n in &mut self.normals {
+            let [x, y, z] = normalize(normal_rows.map(|r| dot(r, [n.x, n.y, n.z])));
+            (n.x, n.y, n.z) = (x, y, z);
+        }
+        if det < 0.0 {
+            for f in &mut self.faces {
+                if let Some(rest) = f.elements.get_mut(1..) {
+                    rest.reverse();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the model so the center of the box around its vertices is at
+    /// the origin.
+    pub fn recenter(&mut self) {
+        let points = self
+            .vertices
+            .iter()
+            .map(|v| [v.x, v.y, v.z])
+            .collect::<Vec<_>>();
+        if let Some(b) = bounding_box(&points) {
+            let [x, y, z] = b.center();
+            // Translations can always be inverted.
+            let _ = self.transform(&Transform::translation(-x, -y, -z));
+        }
+    }
+
+    /// Scales the model about the origin, by the same amount along every
+    /// axis, so the longest side of the box around its vertices is `size`.
+    /// Nothing is changed if all the vertices are in the same place, and an
+    /// error is returned if `size` is 0.
+    pub fn rescale(&mut self, size: f32) -> Result<(), TransformError> {
+        let points = self
+            .vertices
+            .iter()
+            .map(|v| [v.x, v.y, v.z])
+            .collect::<Vec<_>>();
+        let longest =
+            bounding_box(&points).map_or(0.0, |b| b.size().into_iter().fold(0.0, f32::max));
+        if longest == 0.0 {
+            return Ok(());
+        }
+        let s = size / longest;
+        self.transform(&Transform::scale(s, s, s))
+    }
+}
@@ -0,0 +1,164 @@
+use std::result::Result;
+
+use crate::{
+    math::{cross, dot, normalize, polygon_normal},
+    ElementLocation, Face, IndexError, Model, ValidationError,
+};
+
+/// How faces with more than three elements are split into triangles.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Triangulation {
+    /// Ear clipping in the plane which best fits the face. This handles
+    /// concave faces.
+    #[default]
+    EarClipping,
+    /// Triangles fanning out from the first element. This is faster, but
+    /// only correct for convex faces.
+    Fan,
+}
+
+impl Face {
+    /// Splits the face into triangles. Each triangle holds the positions in
+    /// `Face::elements` of its corners, in the winding order of the face.
+    ///
+    /// A face with fewer than three elements produces no triangles, and
+    /// one with `n` elements produces `n - 2` of them.
+    pub fn triangulate(
+        &self,
+        model: &Model,
+        method: Triangulation,
+    ) -> Result<Vec<[usize; 3]>, IndexError> {
+        if method == Triangulation::Fan {
+            return Ok(fan(self.elements.len()));
+        }
+        let points = self
+            .elements
+            .iter()
+            .map(|e| {
+                model
+                    .vertex_index(e.vertex_index)
+                    .map(|v| position(model, v))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(triangulate(&points, method))
+    }
+}
+
+impl Model {
+    /// Splits every face into triangles, as described for
+    /// `Face::triangulate`. The result has one entry for each entry in
+    /// `Model::faces`.
+    pub fn triangulate(
+        &self,
+        method: Triangulation,
+    ) -> Result<Vec<Vec<[usize; 3]>>, ValidationError> {
+        self.faces
+            .iter()
+            .enumerate()
+            .map(|(face, f)| {
+                let points = f
+                    .elements
+                    .iter()
+                    .enumerate()
+                    .map(|(element, e)| {
+                        self.vertex_index(e.vertex_index)
+                            .map(|v| position(self, v))
+                            .map_err(|error| ValidationError::Index {
+                                location: ElementLocation::Face { face, element },
+                                error,
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(triangulate(&points, method))
+            })
+            .collect()
+    }
+}
+
+fn position(model: &Model, index: usize) -> [f32; 3] {
+    let v = &model.vertices[index];
+    [v.x, v.y, v.z]
+}
+
+/// Splits a polygon into triangles, returning the positions of their
+/// corners in `points`.
+pub(crate) fn triangulate(points: &[[f32; 3]], method: Triangulation) -> Vec<[usize; 3]> {
+    if method == Triangulation::Fan || points.len() <= 3 {
+        return fan(points.len());
+    }
+
+    // The polygon winds counter-clockwise around the normal.
+    let normal = normalize(polygon_normal(points));
+    if dot(normal, normal) < 0.5 {
+        // Every point is on a line, so any split is as good as another.
+        return fan(points.len());
+    }
+    let axis = if normal[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    let u = normalize(cross(axis, normal));
+    let v = cross(normal, u);
+    let projected = points
+        .iter()
+        .map(|p| [dot(*p, u), dot(*p, v)])
+        .collect::<Vec<_>>();
+    ear_clip(&projected)
+}
+
+fn fan(count: usize) -> Vec<[usize; 3]> {
+    (1..count.saturating_sub(1))
+        .map(|i| [0, i, i + 1])
+        .collect()
+}
+
+/// Ear clipping of a counter-clockwise polygon.
+fn ear_clip(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| {
+            [
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            ]
+        };
+        let is_ear = |i: usize| {
+            let [a, b, c] = corner(i).map(|j| points[j]);
+            turn(a, b, c) > 0.0
+                && remaining.iter().all(|j| {
+                    let p = points[*j];
+                    p == a || p == b || p == c || !inside(a, b, c, p)
+                })
+        };
+        // A polygon always has an ear, but rounding or self-intersections
+        // can hide it. Clipping a convex corner, or failing that any corner,
+        // still makes progress.
+        let i = (0..n)
+            .find(|i| is_ear(*i))
+            .or_else(|| {
+                (0..n).find(|i| {
+                    let [a, b, c] = corner(*i).map(|j| points[j]);
+                    turn(a, b, c) >= 0.0
+                })
+            })
+            .unwrap_or(0);
+        triangles.push(corner(i));
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+/// Twice the signed area of the triangle, positive when it winds
+/// counter-clockwise.
+fn turn(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn inside(a: [f32; 2], b: [f32; 2], c: [f32; 2], p: [f32; 2]) -> bool {
+    turn(a, b, p) >= 0.0 && turn(b, c, p) >= 0.0 && turn(c, a, p) >= 0.0
+}